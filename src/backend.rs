@@ -0,0 +1,411 @@
+use crate::error::{AppError, AppResult};
+use crate::invidious::{InvidiousBackend, PipedBackend};
+use crate::share::{
+    SearchBackendKind, SearchOrder, SearchQuery, SearchResponse, SearchType, SettingsState,
+};
+use crate::worker::{YouTubeClient, call_yt_dlp_listing, resolve_api_base_url, resolve_api_key};
+use reqwest::Client;
+use std::future::Future;
+use std::pin::Pin;
+
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = AppResult<T>> + Send + 'a>>;
+
+// Source of search results. Every backend returns pages with durations already filled in,
+// the page token is opaque to the UI.
+pub trait SearchBackend: Send + Sync {
+    fn search(
+        &self,
+        query: SearchQuery,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse>;
+
+    fn playlist_videos(
+        &self,
+        playlist_id: String,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse>;
+
+    fn channel_videos(
+        &self,
+        channel_id: String,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse>;
+}
+
+pub fn has_api_key(personal_yt_api: &str) -> bool {
+    resolve_api_key(personal_yt_api).is_some()
+}
+
+// The selected backend, or yt-dlp if the Data API is selected without a key
+pub fn effective_backend_kind(
+    selected: SearchBackendKind,
+    personal_yt_api: &str,
+) -> SearchBackendKind {
+    match selected {
+        SearchBackendKind::DataApi if !has_api_key(personal_yt_api) => SearchBackendKind::YtDlp,
+        kind => kind,
+    }
+}
+
+pub fn create_backend(
+    kind: SearchBackendKind,
+    settings: &SettingsState,
+    http: Client,
+) -> Box<dyn SearchBackend> {
+    let base_url = settings
+        .selected_search_profile()
+        .instance_url
+        .trim()
+        .to_string();
+    match kind {
+        SearchBackendKind::DataApi => {
+            let api_key = resolve_api_key(&settings.personal_yt_api).unwrap_or_default();
+            let base_url = resolve_api_base_url(&settings.api_base_url);
+            Box::new(DataApiBackend {
+                client: YouTubeClient::new(http, base_url, api_key),
+            })
+        }
+        SearchBackendKind::YtDlp => Box::new(YtDlpBackend),
+        SearchBackendKind::Invidious => Box::new(InvidiousBackend { http, base_url }),
+        SearchBackendKind::Piped => Box::new(PipedBackend { http, base_url }),
+    }
+}
+
+// YouTube Data API v3 (needs a key)
+pub struct DataApiBackend {
+    pub client: YouTubeClient,
+}
+
+impl DataApiBackend {
+    async fn with_durations(&self, mut data: SearchResponse) -> AppResult<SearchResponse> {
+        let video_ids: Vec<String> = data
+            .items
+            .iter()
+            .filter_map(|item| item.id.video_id.clone())
+            .collect();
+        if !video_ids.is_empty() {
+            self.client
+                .set_video_durration(video_ids, &mut data)
+                .await?;
+        }
+        Ok(data)
+    }
+}
+
+impl SearchBackend for DataApiBackend {
+    fn search(
+        &self,
+        query: SearchQuery,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            let data = self.client.call_yt_api(query, page_token).await?;
+            self.with_durations(data).await
+        })
+    }
+
+    fn playlist_videos(
+        &self,
+        playlist_id: String,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            let data = self
+                .client
+                .call_playlist_items(playlist_id, max_results, page_token)
+                .await?;
+            self.with_durations(data).await
+        })
+    }
+
+    fn channel_videos(
+        &self,
+        channel_id: String,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            let uploads = self.client.get_channel_uploads(channel_id).await?;
+            let data = self
+                .client
+                .call_playlist_items(uploads, max_results, page_token)
+                .await?;
+            self.with_durations(data).await
+        })
+    }
+}
+
+// Key-less search through the bundled yt-dlp binary (ytsearchN:<query> --flat-playlist).
+// Only finds videos; the API filters are not available, the client-side filters still apply.
+// The page token is the number of entries already loaded.
+pub struct YtDlpBackend;
+
+impl YtDlpBackend {
+    fn offset(page_token: Option<String>) -> usize {
+        page_token.and_then(|t| t.parse().ok()).unwrap_or(0)
+    }
+}
+
+impl SearchBackend for YtDlpBackend {
+    fn search(
+        &self,
+        query: SearchQuery,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            if query.search_type != SearchType::Video && query.search_type != SearchType::All {
+                return Err(AppError::Other(
+                    "yt-dlp search only finds videos. Choose the Data API, Invidious or Piped backend for channels and playlists."
+                        .to_string(),
+                ));
+            }
+            let offset = Self::offset(page_token);
+            let count = query.max_results.max(1) as usize;
+            let prefix = match query.filters.order {
+                SearchOrder::Date => "ytsearchdate",
+                _ => "ytsearch",
+            };
+            let target = format!("{prefix}{}:{}", offset + count, query.text);
+            call_yt_dlp_listing(target, offset, count).await
+        })
+    }
+
+    fn playlist_videos(
+        &self,
+        playlist_id: String,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            let target = format!("https://www.youtube.com/playlist?list={playlist_id}");
+            let offset = Self::offset(page_token);
+            call_yt_dlp_listing(target, offset, max_results.max(1) as usize).await
+        })
+    }
+
+    fn channel_videos(
+        &self,
+        channel_id: String,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            let target = format!("https://www.youtube.com/channel/{channel_id}/videos");
+            let offset = Self::offset(page_token);
+            call_yt_dlp_listing(target, offset, max_results.max(1) as usize).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duration::format_duration;
+    use crate::logging::capture;
+    use crate::share::SearchProfile;
+    use crate::test_server::TestServer;
+    use crate::worker::build_http_client;
+
+    fn data_api(server: &TestServer) -> DataApiBackend {
+        DataApiBackend {
+            client: YouTubeClient::new(
+                build_http_client(),
+                server.base_url.clone(),
+                "test-key".to_string(),
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn search_fills_in_durations_from_videos_endpoint() {
+        let server = TestServer::start(vec![
+            (
+                "/search",
+                include_str!("../tests/fixtures/data_api_search.json"),
+            ),
+            (
+                "/videos",
+                include_str!("../tests/fixtures/data_api_videos.json"),
+            ),
+        ])
+        .await;
+        let query = SearchQuery {
+            text: "daft punk".to_string(),
+            max_results: 2,
+            ..Default::default()
+        };
+
+        let data = data_api(&server)
+            .search(query, Some("CAIQAA".to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(data.next_page_token, "CAIQAQ");
+        let labels: Vec<String> = data
+            .items
+            .iter()
+            .map(|item| format_duration(item.video_durration.unwrap()))
+            .collect();
+        assert_eq!(labels, ["5:21", "1:02:03"]);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("/search?"));
+        assert!(requests[0].contains("q=daft+punk"));
+        assert!(requests[0].contains("pageToken=CAIQAA"));
+        assert!(requests[1].starts_with("/videos?"));
+        assert!(requests[1].contains("id=5NV6Rdv1a3I%2CgAjR4_CbPpQ"));
+    }
+
+    #[tokio::test]
+    async fn channel_drill_down_resolves_uploads_playlist() {
+        let server = TestServer::start(vec![
+            (
+                "/channels",
+                include_str!("../tests/fixtures/data_api_channels.json"),
+            ),
+            (
+                "/playlistItems",
+                include_str!("../tests/fixtures/data_api_playlist_items.json"),
+            ),
+            (
+                "/videos",
+                include_str!("../tests/fixtures/data_api_videos.json"),
+            ),
+        ])
+        .await;
+
+        let data = data_api(&server)
+            .channel_videos("UC_kRDKYrUlrbtrSiyu5Tflg".to_string(), 10, None)
+            .await
+            .unwrap();
+
+        // the deleted video without resourceId.videoId is dropped
+        assert_eq!(data.items.len(), 1);
+        assert_eq!(data.items[0].id.video_id.as_deref(), Some("5NV6Rdv1a3I"));
+        assert_eq!(data.items[0].snippet.channel_title, "Daft Punk");
+        assert!(data.items[0].video_durration.is_some());
+        assert!(server.requests()[1].contains("playlistId=UU_kRDKYrUlrbtrSiyu5Tflg"));
+    }
+
+    #[test]
+    fn search_profiles_carry_their_own_instance() {
+        let mut settings = SettingsState::default();
+        assert_eq!(settings.selected_backend(), SearchBackendKind::DataApi);
+        settings.search_profiles.push(SearchProfile {
+            name: "yewtu.be".to_string(),
+            kind: SearchBackendKind::Invidious,
+            instance_url: "https://yewtu.be".to_string(),
+        });
+        settings.search_profile = "yewtu.be".to_string();
+        assert_eq!(settings.selected_backend(), SearchBackendKind::Invidious);
+        assert_eq!(
+            settings.selected_search_profile().instance_url,
+            "https://yewtu.be"
+        );
+        // a deleted or renamed profile falls back to the first one
+        settings.search_profile = "gone".to_string();
+        assert_eq!(settings.selected_backend(), SearchBackendKind::DataApi);
+    }
+
+    #[tokio::test]
+    async fn missing_key_fails_without_a_request() {
+        let server = TestServer::start(Vec::new()).await;
+        let backend = DataApiBackend {
+            client: YouTubeClient::new(build_http_client(), server.base_url.clone(), String::new()),
+        };
+        let error = backend.search(SearchQuery::default(), None).await;
+        assert_eq!(error.unwrap_err(), AppError::MissingKey);
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn validate_key_explains_google_errors() {
+        let server = TestServer::start_with_status(vec![(
+            "/videos",
+            400,
+            include_str!("../tests/fixtures/data_api_key_invalid.json"),
+        )])
+        .await;
+        let error = data_api(&server).client.validate_key().await.unwrap_err();
+        assert_eq!(error, AppError::InvalidKey);
+        assert!(error.to_string().contains("not valid"), "{error}");
+
+        let server = TestServer::start_with_status(vec![(
+            "/videos",
+            403,
+            include_str!("../tests/fixtures/data_api_quota_exceeded.json"),
+        )])
+        .await;
+        let error = data_api(&server).client.validate_key().await.unwrap_err();
+        assert_eq!(error, AppError::QuotaExceeded);
+
+        let server = TestServer::start(vec![(
+            "/videos",
+            r#"{"kind":"youtube#videoListResponse","items":[]}"#,
+        )])
+        .await;
+        assert_eq!(data_api(&server).client.validate_key().await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn api_key_never_appears_in_logs_or_errors() {
+        let secret = "AIzaSy-test-secret-never-log-me";
+        let server = TestServer::start(vec![
+            (
+                "/search",
+                include_str!("../tests/fixtures/data_api_search.json"),
+            ),
+            (
+                "/videos",
+                include_str!("../tests/fixtures/data_api_videos.json"),
+            ),
+        ])
+        .await;
+        let backend = DataApiBackend {
+            client: YouTubeClient::new(build_http_client(), server.base_url.clone(), secret.into()),
+        };
+
+        capture::start();
+        backend.search(SearchQuery::default(), None).await.unwrap();
+        let lines = capture::finish();
+        assert!(!lines.is_empty());
+        assert!(lines.iter().all(|line| !line.contains(secret)), "{lines:?}");
+        // the key really was sent
+        assert!(server.requests()[0].contains(secret));
+
+        // nobody listens on this port, the connect error must not echo the URL
+        let backend = DataApiBackend {
+            client: YouTubeClient::new(
+                build_http_client(),
+                "http://127.0.0.1:9".to_string(),
+                secret.into(),
+            ),
+        };
+        capture::start();
+        let error = backend
+            .search(SearchQuery::default(), None)
+            .await
+            .unwrap_err()
+            .to_string();
+        let lines = capture::finish();
+        assert!(!error.contains(secret), "{error}");
+        assert!(lines.iter().all(|line| !line.contains(secret)), "{lines:?}");
+    }
+
+    #[test]
+    fn key_from_settings_wins() {
+        assert_eq!(resolve_api_key("  my-key "), Some("my-key".to_string()));
+    }
+
+    #[test]
+    fn configured_base_url_wins() {
+        assert_eq!(
+            resolve_api_base_url("http://localhost:8080/youtube/v3/"),
+            "http://localhost:8080/youtube/v3"
+        );
+    }
+}
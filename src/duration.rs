@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+// Parses ISO 8601 durations as returned by contentDetails.duration (e.g. PT1H2M3S, P1DT2H, P0D).
+// Years and months have no fixed length and are rejected.
+pub fn parse_iso8601_duration(value: &str) -> Option<Duration> {
+    let rest = value.strip_prefix('P')?;
+    if rest.is_empty() {
+        return None;
+    }
+    let (date_part, time_part) = match rest.split_once('T') {
+        Some((_, "")) => return None,
+        Some((date, time)) => (date, Some(time)),
+        None => (rest, None),
+    };
+
+    let mut seconds = 0.0;
+    for (amount, unit) in components(date_part)? {
+        seconds += amount
+            * match unit {
+                'W' => 7.0 * 86_400.0,
+                'D' => 86_400.0,
+                _ => return None,
+            };
+    }
+    if let Some(time_part) = time_part {
+        for (amount, unit) in components(time_part)? {
+            seconds += amount
+                * match unit {
+                    'H' => 3_600.0,
+                    'M' => 60.0,
+                    'S' => 1.0,
+                    _ => return None,
+                };
+        }
+    }
+    // API data: an absurd value must not panic
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+// Splits "1H2M3.5S" into (1, 'H'), (2, 'M'), (3.5, 'S')
+fn components(part: &str) -> Option<Vec<(f64, char)>> {
+    let mut result = Vec::new();
+    let mut number = String::new();
+    for c in part.chars() {
+        if c.is_ascii_digit() || c == '.' || c == ',' {
+            number.push(if c == ',' { '.' } else { c });
+        } else {
+            let amount: f64 = number.parse().ok()?;
+            result.push((amount, c));
+            number.clear();
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(result)
+}
+
+// H:MM:SS for anything longer than an hour, otherwise M:SS
+pub fn format_duration(duration: Duration) -> String {
+    let total = duration.as_secs();
+    let (hours, minutes, seconds) = (total / 3_600, (total % 3_600) / 60, total % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(value: &str) -> Option<u64> {
+        parse_iso8601_duration(value).map(|d| d.as_secs())
+    }
+
+    #[test]
+    fn parses_regular_durations() {
+        assert_eq!(secs("PT1H2M3S"), Some(3_723));
+        assert_eq!(secs("PT4M5S"), Some(245));
+        assert_eq!(secs("PT59S"), Some(59));
+    }
+
+    #[test]
+    fn parses_missing_components() {
+        assert_eq!(secs("PT1H"), Some(3_600));
+        assert_eq!(secs("PT1H5S"), Some(3_605));
+        assert_eq!(secs("PT10M"), Some(600));
+    }
+
+    #[test]
+    fn parses_days_and_weeks() {
+        assert_eq!(secs("P1DT2H3M4S"), Some(93_784));
+        assert_eq!(secs("P2D"), Some(172_800));
+        assert_eq!(secs("P1W"), Some(604_800));
+    }
+
+    #[test]
+    fn parses_live_zero_duration() {
+        assert_eq!(secs("P0D"), Some(0));
+        assert_eq!(secs("PT0S"), Some(0));
+    }
+
+    #[test]
+    fn parses_fractional_seconds() {
+        let duration = parse_iso8601_duration("PT1.5S").unwrap();
+        assert_eq!(duration.as_millis(), 1_500);
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert_eq!(secs(""), None);
+        assert_eq!(secs("P"), None);
+        assert_eq!(secs("PT"), None);
+        assert_eq!(secs("1H2M"), None);
+        assert_eq!(secs("PT5"), None);
+        assert_eq!(secs("PTxS"), None);
+        assert_eq!(secs("P1Y"), None);
+        assert_eq!(secs("P1M"), None);
+        assert_eq!(secs("PT1D"), None);
+        assert_eq!(secs("P99999999999999999999W"), None);
+    }
+
+    #[test]
+    fn formats_for_display() {
+        assert_eq!(format_duration(Duration::from_secs(245)), "4:05");
+        assert_eq!(format_duration(Duration::from_secs(3_600)), "1:00:00");
+        assert_eq!(format_duration(Duration::from_secs(3_723)), "1:02:03");
+        assert_eq!(format_duration(Duration::from_secs(93_784)), "26:03:04");
+        assert_eq!(format_duration(Duration::ZERO), "0:00");
+    }
+}
@@ -0,0 +1,272 @@
+use std::fmt;
+use std::io;
+
+// Everything the worker can fail with. The Display text is what the user sees, so every
+// variant says what to do about it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    // no connection, DNS failure, timeout
+    Network(String),
+    // non-2xx answer; message comes from the Google/instance error body if there is one
+    Http {
+        status: u16,
+        message: String,
+    },
+    QuotaExceeded,
+    InvalidKey,
+    MissingKey,
+    // yt-dlp ran but failed; the last lines of its stderr explain why
+    YtDlpFailed {
+        code: Option<i32>,
+        stderr_tail: Vec<String>,
+    },
+    // ffmpeg ran but failed, with its last stderr line
+    FfmpegFailed(String),
+    MissingBinary(String),
+    Io(String),
+    // answer that could not be decoded
+    InvalidResponse(String),
+    InvalidUrl(String),
+    Cancelled,
+    Other(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+// Lines of yt-dlp stderr kept for the error message
+pub const STDERR_TAIL_LINES: usize = 5;
+
+impl AppError {
+    // Maps a Google error body ({"error": {"errors": [{"reason": ...}]}}) onto the matching variant
+    pub fn from_api_error(status: reqwest::StatusCode, body: &serde_json::Value) -> Self {
+        let error = body.get("error");
+        let mut reasons: Vec<&str> = Vec::new();
+        for list in ["errors", "details"] {
+            if let Some(entries) = error.and_then(|e| e.get(list)).and_then(|v| v.as_array()) {
+                reasons.extend(entries.iter().filter_map(|e| e.get("reason")?.as_str()));
+            }
+        }
+        let has = |reason: &str| reasons.contains(&reason);
+
+        let message = if has("API_KEY_INVALID") || has("keyInvalid") {
+            return AppError::InvalidKey;
+        } else if has("quotaExceeded") || has("dailyLimitExceeded") || has("rateLimitExceeded") {
+            return AppError::QuotaExceeded;
+        } else if has("accessNotConfigured") || has("SERVICE_DISABLED") {
+            "The YouTube Data API v3 is not enabled for this key's Google Cloud project."
+                .to_string()
+        } else if has("API_KEY_HTTP_REFERRER_BLOCKED")
+            || has("API_KEY_IP_ADDRESS_BLOCKED")
+            || has("API_KEY_SERVICE_BLOCKED")
+            || has("forbidden")
+        {
+            "This API key is restricted and may not be used from this app.".to_string()
+        } else {
+            // Google: error.message, Invidious: error, Piped: message
+            error
+                .and_then(|e| e.get("message"))
+                .or(error)
+                .or(body.get("message"))
+                .and_then(|m| m.as_str())
+                .unwrap_or("no details")
+                .to_string()
+        };
+        AppError::Http {
+            status: status.as_u16(),
+            message,
+        }
+    }
+
+    pub fn yt_dlp_failed(code: Option<i32>, stderr: &[String]) -> Self {
+        let tail = stderr.len().saturating_sub(STDERR_TAIL_LINES);
+        AppError::YtDlpFailed {
+            code,
+            stderr_tail: stderr[tail..].to_vec(),
+        }
+    }
+
+    // Spawning yt-dlp: a missing file gets its own variant
+    pub fn spawn_failed(binary: &str, error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::NotFound {
+            AppError::MissingBinary(binary.to_string())
+        } else {
+            AppError::Io(error.to_string())
+        }
+    }
+
+    // Longer text for tooltips, e.g. the whole stderr tail
+    pub fn details(&self) -> Option<String> {
+        match self {
+            AppError::YtDlpFailed { stderr_tail, .. } if !stderr_tail.is_empty() => {
+                Some(stderr_tail.join("\n"))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Network(detail) => write!(
+                f,
+                "Could not reach the server ({detail}). Check your internet connection."
+            ),
+            AppError::Http { status, message } => {
+                write!(f, "Request failed with HTTP {status}: {message}")
+            }
+            AppError::QuotaExceeded => f.write_str(
+                "The daily quota of this API key is used up. Try again tomorrow, use another key or switch to the yt-dlp backend.",
+            ),
+            AppError::InvalidKey => f.write_str(
+                "The API key is not valid. Check that it was copied completely and enter it again in the settings.",
+            ),
+            AppError::MissingKey => f.write_str(
+                "No YouTube API key configured. Enter one in the settings or use the yt-dlp backend.",
+            ),
+            AppError::YtDlpFailed { code, stderr_tail } => {
+                match code {
+                    Some(code) => write!(f, "yt-dlp failed with exit code {code}")?,
+                    None => f.write_str("yt-dlp was terminated")?,
+                }
+                // the last ERROR line usually says the most
+                let reason = stderr_tail
+                    .iter()
+                    .rev()
+                    .find(|line| line.starts_with("ERROR"))
+                    .or(stderr_tail.last());
+                match reason {
+                    Some(reason) => write!(f, ": {reason}"),
+                    None => Ok(()),
+                }
+            }
+            AppError::FfmpegFailed(detail) => write!(f, "ffmpeg failed: {detail}"),
+            AppError::MissingBinary(path) => write!(
+                f,
+                "{path} was not found. Put the binary next to the app and restart it."
+            ),
+            AppError::Io(detail) => write!(
+                f,
+                "File error: {detail}. Check the download folder in the settings."
+            ),
+            AppError::InvalidResponse(detail) => {
+                write!(f, "The server sent an unexpected answer: {detail}")
+            }
+            AppError::InvalidUrl(detail) => write!(
+                f,
+                "Invalid URL ({detail}). Check the base URLs in the settings."
+            ),
+            AppError::Cancelled => f.write_str("Download cancelled"),
+            AppError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<reqwest::Error> for AppError {
+    fn from(error: reqwest::Error) -> Self {
+        // the URL contains the API key
+        let error = error.without_url();
+        if error.is_decode() {
+            AppError::InvalidResponse(error.to_string())
+        } else {
+            AppError::Network(error.to_string())
+        }
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(error: io::Error) -> Self {
+        AppError::Io(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(error: serde_json::Error) -> Self {
+        AppError::InvalidResponse(error.to_string())
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Other(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Other(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    #[test]
+    fn maps_google_reasons() {
+        let body = json!({"error": {"errors": [{"reason": "quotaExceeded"}]}});
+        assert_eq!(
+            AppError::from_api_error(StatusCode::FORBIDDEN, &body),
+            AppError::QuotaExceeded
+        );
+        let body = json!({"error": {"details": [{"reason": "API_KEY_INVALID"}]}});
+        assert_eq!(
+            AppError::from_api_error(StatusCode::BAD_REQUEST, &body),
+            AppError::InvalidKey
+        );
+        let body = json!({"error": {"code": 404, "message": "Channel not found."}});
+        assert_eq!(
+            AppError::from_api_error(StatusCode::NOT_FOUND, &body),
+            AppError::Http {
+                status: 404,
+                message: "Channel not found.".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn reads_instance_error_bodies() {
+        let body = json!({"error": "Rate limited"});
+        let error = AppError::from_api_error(StatusCode::TOO_MANY_REQUESTS, &body);
+        assert_eq!(
+            error.to_string(),
+            "Request failed with HTTP 429: Rate limited"
+        );
+        let body = serde_json::Value::Null;
+        let error = AppError::from_api_error(StatusCode::BAD_GATEWAY, &body);
+        assert_eq!(
+            error.to_string(),
+            "Request failed with HTTP 502: no details"
+        );
+    }
+
+    #[test]
+    fn yt_dlp_errors_keep_the_stderr_tail() {
+        let stderr: Vec<String> = (1..=8).map(|i| format!("line {i}")).collect();
+        let mut stderr = stderr;
+        stderr.insert(6, "ERROR: [youtube] abc: Video unavailable".to_string());
+        let error = AppError::yt_dlp_failed(Some(1), &stderr);
+        let AppError::YtDlpFailed { stderr_tail, .. } = &error else {
+            panic!("{error:?}");
+        };
+        assert_eq!(stderr_tail.len(), STDERR_TAIL_LINES);
+        assert_eq!(stderr_tail.last().unwrap(), "line 8");
+        assert_eq!(
+            error.to_string(),
+            "yt-dlp failed with exit code 1: ERROR: [youtube] abc: Video unavailable"
+        );
+    }
+
+    #[test]
+    fn missing_binary_is_recognised() {
+        let error = io::Error::from(io::ErrorKind::NotFound);
+        assert_eq!(
+            AppError::spawn_failed("./yt_dlp/yt-dlp", error),
+            AppError::MissingBinary("./yt_dlp/yt-dlp".to_string())
+        );
+    }
+}
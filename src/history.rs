@@ -0,0 +1,245 @@
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+// One finished download
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HistoryEntry {
+    pub video_id: String,
+    pub title: String,
+    pub files: Vec<PathBuf>,
+    pub profile: String,
+    // profile summary, e.g. "MP3 · 192 kbit/s"
+    pub format: String,
+    // unix seconds
+    pub downloaded_at: u64,
+    // bytes of all files together
+    pub size: u64,
+}
+
+impl HistoryEntry {
+    // Entry for files that were just written; size is read from disk
+    pub fn new(
+        video_id: String,
+        title: String,
+        files: Vec<PathBuf>,
+        profile: String,
+        format: String,
+    ) -> Self {
+        let size = files
+            .iter()
+            .filter_map(|file| std::fs::metadata(file).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        let downloaded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self {
+            video_id,
+            title,
+            files,
+            profile,
+            format,
+            downloaded_at,
+            size,
+        }
+    }
+}
+
+// Everything downloaded so far. Our own record lives in history.json, yt-dlp keeps its
+// --download-archive file next to it; a video counts as downloaded if either knows it.
+#[derive(Debug, Default)]
+pub struct DownloadHistory {
+    entries: Vec<HistoryEntry>,
+    archived: HashSet<String>,
+    dir: Option<PathBuf>,
+}
+
+fn data_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "rust-journey").map(|dirs| dirs.data_dir().to_path_buf())
+}
+
+impl DownloadHistory {
+    pub fn load() -> Self {
+        match data_dir() {
+            Some(dir) => Self::load_from(dir),
+            None => {
+                warn!("no data directory, the download history is not saved");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn load_from(dir: PathBuf) -> Self {
+        let entries = match std::fs::read_to_string(dir.join("history.json")) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|error| {
+                warn!(%error, "history.json could not be read, starting empty");
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        let archived = std::fs::read_to_string(dir.join("archive.txt"))
+            .map(|archive| parse_archive(&archive))
+            .unwrap_or_default();
+        Self {
+            entries,
+            archived,
+            dir: Some(dir),
+        }
+    }
+
+    // Path handed to yt-dlp --download-archive
+    pub fn archive_path(&self) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join("archive.txt"))
+    }
+
+    pub fn contains(&self, video_id: &str) -> bool {
+        self.archived.contains(video_id) || self.latest(video_id).is_some()
+    }
+
+    pub fn latest(&self, video_id: &str) -> Option<&HistoryEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.video_id == video_id)
+    }
+
+    // yt-dlp skipped the video because its archive already had it
+    pub fn mark_archived(&mut self, video_id: String) {
+        self.archived.insert(video_id);
+    }
+
+    pub fn record(&mut self, entry: HistoryEntry) -> io::Result<()> {
+        self.archived.insert(entry.video_id.clone());
+        self.entries.push(entry);
+        self.save()
+    }
+
+    // Removes the video from the history and the archive, so yt-dlp downloads it again
+    pub fn forget(&mut self, video_id: &str) -> io::Result<()> {
+        self.entries.retain(|entry| entry.video_id != video_id);
+        self.archived.remove(video_id);
+        if let Some(path) = self.archive_path()
+            && path.exists()
+        {
+            let archive = std::fs::read_to_string(&path)?;
+            let kept: String = archive
+                .lines()
+                .filter(|line| archive_id(line) != Some(video_id))
+                .map(|line| format!("{line}\n"))
+                .collect();
+            std::fs::write(&path, kept)?;
+        }
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(dir)?;
+        let json = serde_json::to_string_pretty(&self.entries)?;
+        write_atomic(&dir.join("history.json"), &json)
+    }
+}
+
+// Archive lines look like "youtube dQw4w9WgXcQ"
+fn archive_id(line: &str) -> Option<&str> {
+    line.split_whitespace().nth(1)
+}
+
+fn parse_archive(archive: &str) -> HashSet<String> {
+    archive
+        .lines()
+        .filter_map(archive_id)
+        .map(str::to_string)
+        .collect()
+}
+
+// Write a temp file first, so a crash doesn't leave half a history
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(temp, path)
+}
+
+// Unix seconds -> "2024-01-31" (UTC)
+pub fn format_date(unix_secs: u64) -> String {
+    // days since 1970 to a date (civil_from_days by Howard Hinnant)
+    let days = (unix_secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ytgui-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn records_survive_a_reload() {
+        let dir = temp_dir("history");
+        std::fs::write(dir.join("archive.txt"), "youtube fromarchive\n").unwrap();
+        let mut history = DownloadHistory::load_from(dir.clone());
+        assert!(history.contains("fromarchive"));
+        assert!(!history.contains("abc"));
+
+        let entry = HistoryEntry {
+            video_id: "abc".to_string(),
+            title: "Song".to_string(),
+            profile: "MP3".to_string(),
+            ..Default::default()
+        };
+        history.record(entry.clone()).unwrap();
+
+        let history = DownloadHistory::load_from(dir.clone());
+        assert!(history.contains("abc"));
+        assert_eq!(history.latest("abc"), Some(&entry));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn forget_removes_the_archive_line() {
+        let dir = temp_dir("forget");
+        std::fs::write(dir.join("archive.txt"), "youtube one\nyoutube two\n").unwrap();
+        let mut history = DownloadHistory::load_from(dir.clone());
+        history.forget("one").unwrap();
+        assert!(!history.contains("one"));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("archive.txt")).unwrap(),
+            "youtube two\n"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_706_659_200), "2024-01-31");
+    }
+}
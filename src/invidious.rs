@@ -0,0 +1,738 @@
+use crate::backend::{BackendFuture, SearchBackend};
+use crate::error::{AppError, AppResult};
+use crate::share::{
+    Id, SearchItem, SearchOrder, SearchQuery, SearchResponse, SearchType, Snippet, ThumbnailData,
+    Thumbnails, VideoDurationFilter,
+};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::time::Duration;
+
+// Search through a public Invidious or Piped instance, for people without a Google API key.
+// Both map their results onto the same SearchResponse the Data API produces.
+
+async fn get_json<T: DeserializeOwned>(http: &Client, url: Url) -> AppResult<T> {
+    let response = http.get(url).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response
+            .json::<serde_json::Value>()
+            .await
+            .unwrap_or_default();
+        return Err(AppError::from_api_error(status, &body));
+    }
+    Ok(response.json::<T>().await?)
+}
+
+// Instances return protocol-relative (//yt3...) or instance-relative (/vi/...) links
+fn absolute_url(base_url: &str, url: &str) -> String {
+    if url.starts_with("//") {
+        format!("https:{url}")
+    } else if url.starts_with('/') {
+        format!("{}{url}", base_url.trim_end_matches('/'))
+    } else {
+        url.to_string()
+    }
+}
+
+// Upper bound of instance requests for one page of results
+const MAX_REQUESTS_PER_PAGE: usize = 5;
+
+// Instances have their own page sizes. A page of `max_results` items is collected from as many
+// instance pages as needed; the page token is "<items already shown>:<instance cursor>", so the
+// next page continues in the middle of an instance page. `fetch` returns the items of the page
+// at a cursor (None for the first) and the cursor of the page after it, if there is one.
+async fn collect_page<F, Fut>(
+    page_token: Option<String>,
+    max_results: i8,
+    fetch: F,
+) -> AppResult<SearchResponse>
+where
+    F: Fn(Option<String>) -> Fut,
+    Fut: Future<Output = AppResult<(Vec<SearchItem>, Option<String>)>>,
+{
+    let (mut skip, mut cursor) = match page_token.as_deref().map(|token| token.split_once(':')) {
+        Some(Some((skip, cursor))) => (
+            skip.parse().unwrap_or(0),
+            (!cursor.is_empty()).then(|| cursor.to_string()),
+        ),
+        _ => (0, page_token),
+    };
+    let wanted = max_results.max(1) as usize;
+    let mut items = Vec::new();
+    for _ in 0..MAX_REQUESTS_PER_PAGE {
+        let (page_items, next) = fetch(cursor.clone()).await?;
+        let available = page_items.len().saturating_sub(skip);
+        let taken = available.min(wanted - items.len());
+        items.extend(page_items.into_iter().skip(skip).take(taken));
+        if taken < available {
+            skip += taken;
+            break;
+        }
+        let Some(next) = next else {
+            return Ok(SearchResponse {
+                items,
+                ..Default::default()
+            });
+        };
+        cursor = Some(next);
+        skip = 0;
+        if items.len() == wanted {
+            break;
+        }
+    }
+    Ok(SearchResponse {
+        next_page_token: format!("{skip}:{}", cursor.unwrap_or_default()),
+        items,
+        ..Default::default()
+    })
+}
+
+fn endpoint(base_url: &str, path: &str, params: &[(&str, String)]) -> AppResult<Url> {
+    if base_url.trim().is_empty() {
+        return Err(AppError::InvalidUrl(
+            "no instance URL configured".to_string(),
+        ));
+    }
+    let url = format!("{}{path}", base_url.trim_end_matches('/'));
+    Url::parse_with_params(&url, params).map_err(|e| AppError::InvalidUrl(e.to_string()))
+}
+
+// --- Invidious (/api/v1) ---
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum InvidiousResult {
+    Video(InvidiousVideo),
+    Channel(InvidiousChannel),
+    Playlist(InvidiousPlaylist),
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct InvidiousVideo {
+    title: String,
+    video_id: String,
+    author: String,
+    author_id: String,
+    description: String,
+    length_seconds: u64,
+    live_now: bool,
+    video_thumbnails: Vec<InvidiousThumbnail>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct InvidiousChannel {
+    author: String,
+    author_id: String,
+    description: String,
+    author_thumbnails: Vec<InvidiousThumbnail>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct InvidiousPlaylist {
+    title: String,
+    playlist_id: String,
+    author: String,
+    author_id: String,
+    playlist_thumbnail: String,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct InvidiousThumbnail {
+    quality: String,
+    url: String,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct InvidiousVideoList {
+    videos: Vec<InvidiousVideo>,
+    continuation: Option<String>,
+}
+
+pub struct InvidiousBackend {
+    pub http: Client,
+    pub base_url: String,
+}
+
+impl InvidiousBackend {
+    fn thumbnail(&self, thumbnails: Vec<InvidiousThumbnail>) -> Thumbnails {
+        // "default" is 120x90 like in the Data API, else the smallest
+        let thumbnail = thumbnails
+            .iter()
+            .position(|t| t.quality == "default")
+            .or_else(|| {
+                (0..thumbnails.len()).min_by_key(|i| thumbnails[*i].width.unwrap_or(u32::MAX))
+            })
+            .map(|index| &thumbnails[index]);
+        match thumbnail {
+            Some(thumb) => Thumbnails::single(ThumbnailData {
+                url: absolute_url(&self.base_url, &thumb.url),
+                width: thumb.width,
+                height: thumb.height,
+            }),
+            None => Thumbnails::default(),
+        }
+    }
+
+    fn video_item(&self, video: InvidiousVideo) -> SearchItem {
+        let snippet = Snippet {
+            channel_id: video.author_id,
+            title: video.title,
+            description: video.description,
+            thumbnails: self.thumbnail(video.video_thumbnails),
+            channel_title: video.author,
+            live_broadcast_content: if video.live_now { "live" } else { "none" }.to_string(),
+            ..Default::default()
+        };
+        let duration = (!video.live_now).then(|| Duration::from_secs(video.length_seconds));
+        SearchItem::new(Id::video(video.video_id), snippet, duration)
+    }
+
+    fn result_item(&self, result: InvidiousResult) -> Option<SearchItem> {
+        match result {
+            InvidiousResult::Video(video) => Some(self.video_item(video)),
+            InvidiousResult::Channel(channel) => {
+                let snippet = Snippet {
+                    channel_id: channel.author_id.clone(),
+                    title: channel.author.clone(),
+                    description: channel.description,
+                    thumbnails: self.thumbnail(channel.author_thumbnails),
+                    channel_title: channel.author,
+                    ..Default::default()
+                };
+                Some(SearchItem::new(
+                    Id::channel(channel.author_id),
+                    snippet,
+                    None,
+                ))
+            }
+            InvidiousResult::Playlist(playlist) => {
+                let thumbnails = match playlist.playlist_thumbnail.as_str() {
+                    "" => Thumbnails::default(),
+                    url => {
+                        Thumbnails::single(ThumbnailData::url(absolute_url(&self.base_url, url)))
+                    }
+                };
+                let snippet = Snippet {
+                    channel_id: playlist.author_id,
+                    title: playlist.title,
+                    thumbnails,
+                    channel_title: playlist.author,
+                    ..Default::default()
+                };
+                Some(SearchItem::new(
+                    Id::playlist(playlist.playlist_id),
+                    snippet,
+                    None,
+                ))
+            }
+            InvidiousResult::Other => None,
+        }
+    }
+
+    fn search_params(query: &SearchQuery, page: usize) -> Vec<(&'static str, String)> {
+        let search_type = match query.search_type {
+            SearchType::Video => "video",
+            SearchType::Channel => "channel",
+            SearchType::Playlist => "playlist",
+            SearchType::All => "all",
+        };
+        let sort_by = match query.filters.order {
+            SearchOrder::Relevance => "relevance",
+            SearchOrder::Date => "upload_date",
+            SearchOrder::ViewCount => "view_count",
+            SearchOrder::Rating => "rating",
+        };
+        let mut params = vec![
+            ("q", query.text.clone()),
+            ("page", page.to_string()),
+            ("type", search_type.to_string()),
+            ("sort_by", sort_by.to_string()),
+        ];
+        if query.filters.video_duration != VideoDurationFilter::Any {
+            params.push((
+                "duration",
+                query.filters.video_duration.api_value().to_string(),
+            ));
+        }
+        if !query.filters.region_code.trim().is_empty() {
+            params.push(("region", query.filters.region_code.trim().to_uppercase()));
+        }
+        params
+    }
+
+    // Invidious counts pages from 1; the cursor is the page number. An empty page is the end.
+    async fn search_page(
+        &self,
+        query: &SearchQuery,
+        cursor: Option<String>,
+    ) -> AppResult<(Vec<SearchItem>, Option<String>)> {
+        let page: usize = cursor.and_then(|c| c.parse().ok()).unwrap_or(1);
+        let params = Self::search_params(query, page);
+        let url = endpoint(&self.base_url, "/api/v1/search", &params)?;
+        let results: Vec<InvidiousResult> = get_json(&self.http, url).await?;
+        let next = (!results.is_empty()).then(|| (page + 1).to_string());
+        let items = results
+            .into_iter()
+            .filter_map(|result| self.result_item(result))
+            .collect();
+        Ok((items, next))
+    }
+
+    async fn playlist_page(
+        &self,
+        playlist_id: &str,
+        cursor: Option<String>,
+    ) -> AppResult<(Vec<SearchItem>, Option<String>)> {
+        let page: usize = cursor.and_then(|c| c.parse().ok()).unwrap_or(1);
+        let path = format!("/api/v1/playlists/{playlist_id}");
+        let url = endpoint(&self.base_url, &path, &[("page", page.to_string())])?;
+        let list: InvidiousVideoList = get_json(&self.http, url).await?;
+        let next = (!list.videos.is_empty()).then(|| (page + 1).to_string());
+        Ok((self.video_items(list.videos), next))
+    }
+
+    // Channel pages are chained through continuation tokens
+    async fn channel_page(
+        &self,
+        channel_id: &str,
+        cursor: Option<String>,
+    ) -> AppResult<(Vec<SearchItem>, Option<String>)> {
+        let path = format!("/api/v1/channels/{channel_id}/videos");
+        let params: Vec<(&str, String)> = cursor
+            .map(|token| vec![("continuation", token)])
+            .unwrap_or_default();
+        let url = endpoint(&self.base_url, &path, &params)?;
+        let list: InvidiousVideoList = get_json(&self.http, url).await?;
+        let next = list.continuation.filter(|token| !token.is_empty());
+        Ok((self.video_items(list.videos), next))
+    }
+
+    fn video_items(&self, videos: Vec<InvidiousVideo>) -> Vec<SearchItem> {
+        videos
+            .into_iter()
+            .map(|video| self.video_item(video))
+            .collect()
+    }
+}
+
+impl SearchBackend for InvidiousBackend {
+    fn search(
+        &self,
+        query: SearchQuery,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            collect_page(page_token, query.max_results, |cursor| {
+                self.search_page(&query, cursor)
+            })
+            .await
+        })
+    }
+
+    fn playlist_videos(
+        &self,
+        playlist_id: String,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            collect_page(page_token, max_results, |cursor| {
+                self.playlist_page(&playlist_id, cursor)
+            })
+            .await
+        })
+    }
+
+    fn channel_videos(
+        &self,
+        channel_id: String,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            collect_page(page_token, max_results, |cursor| {
+                self.channel_page(&channel_id, cursor)
+            })
+            .await
+        })
+    }
+}
+
+// --- Piped ---
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct PipedItem {
+    #[serde(rename = "type")]
+    kind: String,
+    // /watch?v=ID, /channel/ID or /playlist?list=ID
+    url: String,
+    title: Option<String>,
+    name: Option<String>,
+    thumbnail: String,
+    uploader_name: Option<String>,
+    uploader_url: Option<String>,
+    description: Option<String>,
+    short_description: Option<String>,
+    // seconds, -1 for livestreams
+    duration: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct PipedPage {
+    items: Vec<PipedItem>,
+    related_streams: Vec<PipedItem>,
+    nextpage: Option<String>,
+}
+
+pub struct PipedBackend {
+    pub http: Client,
+    pub base_url: String,
+}
+
+impl PipedBackend {
+    fn item(&self, item: PipedItem) -> Option<SearchItem> {
+        let channel_id = item
+            .uploader_url
+            .as_deref()
+            .and_then(|url| url.strip_prefix("/channel/"))
+            .unwrap_or_default()
+            .to_string();
+        let mut snippet = Snippet {
+            channel_id,
+            title: item.title.or(item.name).unwrap_or_default(),
+            description: item
+                .short_description
+                .or(item.description)
+                .unwrap_or_default(),
+            channel_title: item.uploader_name.unwrap_or_default(),
+            ..Default::default()
+        };
+        if !item.thumbnail.is_empty() {
+            snippet.thumbnails = Thumbnails::single(ThumbnailData::url(absolute_url(
+                &self.base_url,
+                &item.thumbnail,
+            )));
+        }
+
+        match item.kind.as_str() {
+            "stream" => {
+                let video_id = item.url.split("v=").nth(1)?.split('&').next()?.to_string();
+                let duration = match item.duration {
+                    Some(seconds) if seconds >= 0 => Some(Duration::from_secs(seconds as u64)),
+                    _ => None,
+                };
+                if item.duration == Some(-1) {
+                    snippet.live_broadcast_content = "live".to_string();
+                }
+                Some(SearchItem::new(Id::video(video_id), snippet, duration))
+            }
+            "channel" => {
+                let channel_id = item.url.strip_prefix("/channel/")?.to_string();
+                snippet.channel_id = channel_id.clone();
+                snippet.channel_title = snippet.title.clone();
+                Some(SearchItem::new(Id::channel(channel_id), snippet, None))
+            }
+            "playlist" => {
+                let playlist_id = item.url.split("list=").nth(1)?.to_string();
+                Some(SearchItem::new(Id::playlist(playlist_id), snippet, None))
+            }
+            _ => None,
+        }
+    }
+
+    fn items(&self, page: PipedPage) -> Vec<SearchItem> {
+        page.items
+            .into_iter()
+            .chain(page.related_streams)
+            .filter_map(|item| self.item(item))
+            .collect()
+    }
+
+    // First page at path, further pages at /nextpage{path} with the nextpage token
+    async fn page(
+        &self,
+        path: &str,
+        mut params: Vec<(&str, String)>,
+        cursor: Option<String>,
+    ) -> AppResult<(Vec<SearchItem>, Option<String>)> {
+        let url = match cursor {
+            Some(token) => {
+                params.push(("nextpage", token));
+                endpoint(&self.base_url, &format!("/nextpage{path}"), &params)?
+            }
+            None => endpoint(&self.base_url, path, &params)?,
+        };
+        let mut page: PipedPage = get_json(&self.http, url).await?;
+        let next = page.nextpage.take().filter(|token| !token.is_empty());
+        Ok((self.items(page), next))
+    }
+}
+
+impl SearchBackend for PipedBackend {
+    fn search(
+        &self,
+        query: SearchQuery,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            let filter = match query.search_type {
+                SearchType::Video => "videos",
+                SearchType::Channel => "channels",
+                SearchType::Playlist => "playlists",
+                SearchType::All => "all",
+            };
+            let params = vec![("q", query.text.clone()), ("filter", filter.to_string())];
+            collect_page(page_token, query.max_results, |cursor| {
+                self.page("/search", params.clone(), cursor)
+            })
+            .await
+        })
+    }
+
+    fn playlist_videos(
+        &self,
+        playlist_id: String,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            let path = format!("/playlists/{playlist_id}");
+            collect_page(page_token, max_results, |cursor| {
+                self.page(&path, Vec::new(), cursor)
+            })
+            .await
+        })
+    }
+
+    fn channel_videos(
+        &self,
+        channel_id: String,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> BackendFuture<'_, SearchResponse> {
+        Box::pin(async move {
+            let path = format!("/channel/{channel_id}");
+            collect_page(page_token, max_results, |cursor| {
+                self.page(&path, Vec::new(), cursor)
+            })
+            .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::TestServer;
+    use crate::worker::build_http_client;
+
+    // the test server answers every page with the same fixture of three results
+    fn query(text: &str, search_type: SearchType) -> SearchQuery {
+        SearchQuery {
+            text: text.to_string(),
+            max_results: 3,
+            search_type,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn invidious_search_maps_all_result_kinds() {
+        let server = TestServer::start(vec![(
+            "/api/v1/search",
+            include_str!("../tests/fixtures/invidious_search.json"),
+        )])
+        .await;
+        let backend = InvidiousBackend {
+            http: build_http_client(),
+            base_url: server.base_url.clone(),
+        };
+
+        let data = backend
+            .search(query("lofi", SearchType::All), None)
+            .await
+            .unwrap();
+
+        assert_eq!(data.items.len(), 3);
+        assert_eq!(data.next_page_token, "0:2");
+        let video = &data.items[0];
+        assert_eq!(video.id.video_id.as_deref(), Some("jfKfPfyJRdk"));
+        assert_eq!(video.snippet.channel_title, "Lofi Girl");
+        assert_eq!(video.video_durration, None);
+        assert_eq!(video.snippet.live_broadcast_content, "live");
+        assert_eq!(
+            data.items[1].id.channel_id.as_deref(),
+            Some("UCSJ4gkVC6NrvII8umztf0Ow")
+        );
+        assert!(
+            data.items[1]
+                .snippet
+                .thumbnails
+                .default
+                .as_ref()
+                .unwrap()
+                .url
+                .starts_with("https://")
+        );
+        assert_eq!(
+            data.items[2].id.playlist_id.as_deref(),
+            Some("PLofht4PTcKYnaH8w5olJCI-wUVxuoMHqM")
+        );
+
+        let requests = server.requests();
+        assert!(requests[0].contains("q=lofi"));
+        assert!(requests[0].contains("type=all"));
+    }
+
+    #[tokio::test]
+    async fn invidious_playlist_keeps_durations() {
+        let server = TestServer::start(vec![(
+            "/api/v1/playlists/PL123",
+            include_str!("../tests/fixtures/invidious_playlist.json"),
+        )])
+        .await;
+        let backend = InvidiousBackend {
+            http: build_http_client(),
+            base_url: server.base_url.clone(),
+        };
+
+        let data = backend
+            .playlist_videos("PL123".to_string(), 2, None)
+            .await
+            .unwrap();
+
+        assert_eq!(data.items.len(), 2);
+        assert_eq!(
+            data.items[0].video_durration,
+            Some(Duration::from_secs(245))
+        );
+        assert_eq!(
+            data.items[1]
+                .snippet
+                .thumbnails
+                .default
+                .as_ref()
+                .unwrap()
+                .url,
+            format!("{}/vi/abc123DEF45/default.jpg", server.base_url)
+        );
+    }
+
+    #[tokio::test]
+    async fn piped_search_and_next_page() {
+        let fixture = include_str!("../tests/fixtures/piped_search.json");
+        let server =
+            TestServer::start(vec![("/search", fixture), ("/nextpage/search", fixture)]).await;
+        let backend = PipedBackend {
+            http: build_http_client(),
+            base_url: server.base_url.clone(),
+        };
+
+        let data = backend
+            .search(query("lofi", SearchType::All), None)
+            .await
+            .unwrap();
+        assert_eq!(data.items.len(), 3);
+        assert_eq!(data.items[0].id.video_id.as_deref(), Some("5qap5aO4i9A"));
+        assert_eq!(
+            data.items[0].video_durration,
+            Some(Duration::from_secs(3_725))
+        );
+        assert_eq!(data.items[0].snippet.channel_id, "UCSJ4gkVC6NrvII8umztf0Ow");
+        assert_eq!(
+            data.items[1].id.channel_id.as_deref(),
+            Some("UCSJ4gkVC6NrvII8umztf0Ow")
+        );
+        assert_eq!(data.items[2].id.playlist_id.as_deref(), Some("PLabc"));
+
+        backend
+            .search(query("lofi", SearchType::All), Some(data.next_page_token))
+            .await
+            .unwrap();
+        let requests = server.requests();
+        assert!(requests[1].starts_with("/nextpage/search?"));
+        assert!(requests[1].contains("nextpage="));
+    }
+
+    fn video(video_id: &str) -> SearchItem {
+        SearchItem::new(Id::video(video_id.to_string()), Snippet::default(), None)
+    }
+
+    // Instance pages of three and two results, then an empty one
+    async fn collect(page_token: Option<String>, max_results: i8) -> (Vec<String>, String) {
+        let data = collect_page(page_token, max_results, |cursor| async move {
+            let page: usize = cursor.and_then(|c| c.parse().ok()).unwrap_or(1);
+            let items = match page {
+                1 => vec![video("a"), video("b"), video("c")],
+                2 => vec![video("d"), video("e")],
+                _ => Vec::new(),
+            };
+            let next = (!items.is_empty()).then(|| (page + 1).to_string());
+            Ok((items, next))
+        })
+        .await
+        .unwrap();
+        let ids = data
+            .items
+            .iter()
+            .filter_map(|item| item.id.video_id.clone())
+            .collect();
+        (ids, data.next_page_token)
+    }
+
+    #[tokio::test]
+    async fn pages_are_cut_to_max_results() {
+        assert_eq!(
+            collect(None, 2).await,
+            (vec!["a".into(), "b".into()], "2:".into())
+        );
+        assert_eq!(
+            collect(Some("2:".into()), 2).await,
+            (vec!["c".into(), "d".into()], "1:2".into())
+        );
+        // the empty instance page ends the list, no "load more" for nothing
+        assert_eq!(
+            collect(Some("1:2".into()), 2).await,
+            (vec!["e".into()], String::new())
+        );
+        assert_eq!(
+            collect(None, 10).await.0,
+            ["a", "b", "c", "d", "e"].map(String::from)
+        );
+        assert_eq!(collect(None, 3).await.1, "0:2");
+    }
+
+    #[tokio::test]
+    async fn instance_errors_are_reported() {
+        let server = TestServer::start(Vec::new()).await;
+        let backend = InvidiousBackend {
+            http: build_http_client(),
+            base_url: server.base_url.clone(),
+        };
+        let error = backend
+            .search(query("lofi", SearchType::Video), None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error,
+            AppError::Http {
+                status: 404,
+                message: "not found".to_string()
+            }
+        );
+    }
+}
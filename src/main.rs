@@ -1,8 +1,27 @@
+mod backend;
+mod duration;
+mod error;
+mod history;
+mod invidious;
 mod locale;
+mod logging;
+mod metadata;
+mod output_template;
+mod playlist;
+mod profiles;
+mod progress;
+mod query_parser;
+mod queue;
 mod share;
+mod split;
+mod tags;
+#[cfg(test)]
+mod test_server;
+mod tracklist;
 mod ui;
 mod worker;
 
+use crate::share::LogLevel;
 use crate::ui::YtGUI;
 use dotenv::dotenv;
 use eframe::egui;
@@ -10,6 +29,8 @@ use eframe::egui;
 #[tokio::main]
 async fn main() {
     dotenv().ok(); // Load environment variables from .env file
+    // the level from the settings is applied in YtGUI::new
+    let _log_guard = logging::init(LogLevel::default());
 
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder {
@@ -22,6 +43,6 @@ async fn main() {
     let app = eframe::run_native("", options, Box::new(|cc| Ok(Box::new(YtGUI::new(cc)))));
 
     if let Err(error) = app {
-        eprintln!("Fehler beim Starten der App: {}", error);
+        tracing::error!(%error, "failed to start the app");
     }
 }
@@ -0,0 +1,276 @@
+use serde::{Deserialize, Deserializer};
+
+// What `yt-dlp -J <url>` says about a single video. Only the fields the app uses are read,
+// everything else in the (large) info dict is ignored.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct VideoMetadata {
+    pub id: String,
+    pub title: String,
+    pub channel: Option<String>,
+    pub uploader: Option<String>,
+    pub description: Option<String>,
+    // seconds
+    pub duration: Option<f64>,
+    pub live_status: Option<LiveStatus>,
+    pub is_live: Option<bool>,
+    #[serde(deserialize_with = "null_as_default")]
+    pub formats: Vec<Format>,
+    #[serde(deserialize_with = "null_as_default")]
+    pub chapters: Vec<Chapter>,
+}
+
+// `yt-dlp --flat-playlist -J <playlist url>`: the entries without their formats
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PlaylistMetadata {
+    pub id: String,
+    pub title: String,
+    #[serde(deserialize_with = "null_as_default")]
+    pub entries: Vec<PlaylistEntry>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PlaylistEntry {
+    pub id: String,
+    pub title: String,
+    pub duration: Option<f64>,
+}
+
+impl PlaylistMetadata {
+    pub fn parse(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiveStatus {
+    NotLive,
+    IsLive,
+    IsUpcoming,
+    WasLive,
+    PostLive,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Format {
+    pub format_id: String,
+    pub format_note: Option<String>,
+    pub ext: Option<String>,
+    // "none" if the stream has no video or audio
+    pub vcodec: Option<String>,
+    pub acodec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    // kbit/s: total, audio, video
+    pub tbr: Option<f64>,
+    pub abr: Option<f64>,
+    pub vbr: Option<f64>,
+    pub filesize: Option<f64>,
+    pub filesize_approx: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Chapter {
+    pub start_time: f64,
+    pub end_time: f64,
+    pub title: String,
+}
+
+// yt-dlp writes `null` instead of an empty list
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+fn has_codec(codec: &Option<String>) -> bool {
+    codec.as_deref().is_some_and(|codec| codec != "none")
+}
+
+impl Format {
+    pub fn has_video(&self) -> bool {
+        has_codec(&self.vcodec)
+    }
+
+    pub fn has_audio(&self) -> bool {
+        has_codec(&self.acodec)
+    }
+
+    // Size in bytes; falls back to yt-dlp's estimate from bitrate and duration
+    pub fn size(&self) -> Option<u64> {
+        self.filesize
+            .or(self.filesize_approx)
+            .map(|size| size as u64)
+    }
+
+    pub fn size_is_exact(&self) -> bool {
+        self.filesize.is_some()
+    }
+
+    // "1920x1080", "audio only"
+    pub fn resolution(&self) -> String {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => format!("{width}x{height}"),
+            (None, Some(height)) => format!("{height}p"),
+            _ if !self.has_video() => "audio only".to_string(),
+            _ => "?".to_string(),
+        }
+    }
+
+    // Format spec for `-f`: video-only streams get the best audio merged in
+    pub fn download_spec(&self) -> String {
+        if self.has_video() && !self.has_audio() {
+            format!("{}+ba", self.format_id)
+        } else {
+            self.format_id.clone()
+        }
+    }
+}
+
+impl VideoMetadata {
+    pub fn parse(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    // Formats that contain audio or video; storyboards (thumbnail sheets) are left out
+    pub fn media_formats(&self) -> impl Iterator<Item = &Format> {
+        self.formats
+            .iter()
+            .filter(|format| format.has_video() || format.has_audio())
+    }
+
+    pub fn format(&self, format_id: &str) -> Option<&Format> {
+        self.formats
+            .iter()
+            .find(|format| format.format_id == format_id)
+    }
+
+    // Size of a download spec like "137+140"; None if a part is unknown or has no size.
+    // "ba" stands for the largest audio-only format, which is what yt-dlp picks in practice.
+    pub fn estimated_size(&self, spec: &str) -> Option<u64> {
+        spec.split('+')
+            .map(|part| match part {
+                "ba" => self
+                    .media_formats()
+                    .filter(|format| !format.has_video())
+                    .filter_map(Format::size)
+                    .max(),
+                id => self.format(id)?.size(),
+            })
+            .sum()
+    }
+
+    // Live streams and premieres cannot be downloaded like normal videos
+    pub fn is_live(&self) -> bool {
+        matches!(
+            self.live_status,
+            Some(LiveStatus::IsLive | LiveStatus::IsUpcoming)
+        ) || self.is_live == Some(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{
+        "id": "abc123",
+        "title": "Some Song",
+        "channel": "Some Artist",
+        "duration": 215.0,
+        "live_status": "not_live",
+        "is_live": false,
+        "chapters": null,
+        "formats": [
+            {"format_id": "sb0", "format_note": "storyboard", "ext": "mhtml", "vcodec": "none", "acodec": "none"},
+            {"format_id": "140", "ext": "m4a", "vcodec": "none", "acodec": "mp4a.40.2", "abr": 129.5, "tbr": 129.5, "filesize": 3481234},
+            {"format_id": "251", "ext": "webm", "vcodec": "none", "acodec": "opus", "abr": 135.1, "filesize_approx": 3600000.7},
+            {"format_id": "137", "ext": "mp4", "vcodec": "avc1.640028", "acodec": "none", "width": 1920, "height": 1080, "fps": 30, "vbr": 2500.0, "filesize": 60000000},
+            {"format_id": "18", "ext": "mp4", "vcodec": "avc1.42001E", "acodec": "mp4a.40.2", "width": 640, "height": 360, "tbr": 500.3}
+        ],
+        "thumbnails": [{"url": "https://i.ytimg.com/vi/abc123/default.jpg"}]
+    }"#;
+
+    #[test]
+    fn parses_the_format_list() {
+        let metadata = VideoMetadata::parse(SAMPLE).unwrap();
+        assert_eq!(metadata.id, "abc123");
+        assert_eq!(metadata.duration, Some(215.0));
+        assert!(metadata.chapters.is_empty());
+        let ids: Vec<&str> = metadata
+            .media_formats()
+            .map(|format| format.format_id.as_str())
+            .collect();
+        assert_eq!(ids, ["140", "251", "137", "18"]);
+
+        let video = metadata.format("137").unwrap();
+        assert_eq!(video.resolution(), "1920x1080");
+        assert_eq!(video.download_spec(), "137+ba");
+        let audio = metadata.format("251").unwrap();
+        assert_eq!(audio.resolution(), "audio only");
+        assert_eq!(audio.size(), Some(3_600_000));
+        assert!(!audio.size_is_exact());
+        assert_eq!(metadata.format("18").unwrap().download_spec(), "18");
+    }
+
+    #[test]
+    fn estimates_merged_sizes() {
+        let metadata = VideoMetadata::parse(SAMPLE).unwrap();
+        assert_eq!(metadata.estimated_size("140"), Some(3_481_234));
+        assert_eq!(metadata.estimated_size("137+140"), Some(63_481_234));
+        // ba = largest audio-only format
+        assert_eq!(metadata.estimated_size("137+ba"), Some(63_600_000));
+        assert_eq!(metadata.estimated_size("18"), None);
+        assert_eq!(metadata.estimated_size("999"), None);
+    }
+
+    #[test]
+    fn reads_chapters_and_live_status() {
+        let json = r#"{
+            "id": "live1",
+            "title": "Stream",
+            "live_status": "is_upcoming",
+            "chapters": [
+                {"start_time": 0.0, "end_time": 60.5, "title": "Intro"},
+                {"start_time": 60.5, "end_time": 300.0, "title": "Main"}
+            ]
+        }"#;
+        let metadata = VideoMetadata::parse(json).unwrap();
+        assert!(metadata.is_live());
+        assert_eq!(metadata.chapters[1].title, "Main");
+        assert_eq!(metadata.chapters[1].start_time, 60.5);
+
+        let json = r#"{"id": "x", "live_status": "something_new"}"#;
+        let metadata = VideoMetadata::parse(json).unwrap();
+        assert_eq!(metadata.live_status, Some(LiveStatus::Unknown));
+        assert!(!metadata.is_live());
+    }
+
+    #[test]
+    fn parses_flat_playlists() {
+        let json = r#"{
+            "_type": "playlist",
+            "id": "PL123",
+            "title": "Mix",
+            "entries": [
+                {"_type": "url", "id": "a1", "title": "First", "duration": 200.0},
+                {"_type": "url", "id": "b2", "title": "[Private video]", "duration": null}
+            ]
+        }"#;
+        let playlist = PlaylistMetadata::parse(json).unwrap();
+        assert_eq!(playlist.title, "Mix");
+        assert_eq!(playlist.entries.len(), 2);
+        assert_eq!(playlist.entries[1].id, "b2");
+        assert_eq!(playlist.entries[1].duration, None);
+    }
+}
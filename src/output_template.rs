@@ -0,0 +1,628 @@
+use crate::playlist::PlaylistPosition;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+// Output template relative to the download folder, same syntax as yt-dlp's -o.
+// On top of the yt-dlp fields the app fills in {artist} and {song} itself.
+pub const DEFAULT_OUTPUT_TEMPLATE: &str = "%(title)s.%(ext)s";
+
+// yt-dlp fields offered as buttons in the editor
+pub const YT_DLP_FIELDS: [&str; 8] = [
+    "title",
+    "channel",
+    "uploader",
+    "upload_date",
+    "id",
+    "playlist_title",
+    "playlist_index",
+    "ext",
+];
+
+// Fields resolved by the app before yt-dlp sees the template
+pub const APP_FIELDS: [(&str, &str); 2] = [
+    (
+        "artist",
+        "artist from an \"Artist - Title\" title, otherwise the channel",
+    ),
+    ("song", "title without the artist part"),
+];
+
+// Downloads land here first and are moved into place once yt-dlp is done
+pub const PARTIAL_DIR: &str = ".partial";
+
+// Characters that are not allowed in file names on at least one platform
+const INVALID_CHARS: [char; 8] = ['<', '>', ':', '"', '\\', '|', '?', '*'];
+
+const MAX_NAME_CHARS: usize = 180;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateError {
+    // Byte range in the template, used for the inline highlighting
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Literal(&'a str),
+    // %(title,channel|Unknown)s: alternatives, default and the conversion ("s", "03d", ...)
+    Field {
+        names: Vec<&'a str>,
+        default: Option<&'a str>,
+        conversion: &'a str,
+    },
+    // {artist}
+    AppField(&'a str),
+}
+
+fn parse(template: &str) -> (Vec<(Range<usize>, Segment<'_>)>, Vec<TemplateError>) {
+    let mut segments = Vec::new();
+    let mut errors = Vec::new();
+    let mut literal_start = 0;
+    let mut index = 0;
+    let bytes = template.as_bytes();
+
+    let flush = |segments: &mut Vec<_>, start: usize, end: usize| {
+        if start < end {
+            segments.push((start..end, Segment::Literal(&template[start..end])));
+        }
+    };
+
+    while index < bytes.len() {
+        match bytes[index] {
+            // %% is a single percent sign
+            b'%' if bytes.get(index + 1) == Some(&b'%') => index += 2,
+            b'%' if bytes.get(index + 1) == Some(&b'(') => {
+                flush(&mut segments, literal_start, index);
+                let Some(close) = template[index..].find(')').map(|i| index + i) else {
+                    errors.push(TemplateError {
+                        span: index..template.len(),
+                        message: "field is not closed, use %(name)s".to_string(),
+                    });
+                    return (segments, errors);
+                };
+                let inner = &template[index + 2..close];
+                if inner.contains('(') {
+                    errors.push(TemplateError {
+                        span: index..index + 2,
+                        message: "field is not closed, use %(name)s".to_string(),
+                    });
+                    return (segments, errors);
+                }
+                let (inner, default) = match inner.split_once('|') {
+                    Some((inner, default)) => (inner, Some(default)),
+                    None => (inner, None),
+                };
+                let names: Vec<&str> = inner.split(',').collect();
+                // flags, width and conversion like printf: 03d, s, .2f
+                let rest = &template[close + 1..];
+                let spec_len = rest
+                    .find(|c: char| !matches!(c, '0'..='9' | '.' | '-' | '+' | '#' | ' '))
+                    .unwrap_or(rest.len());
+                let conversion_end = rest[spec_len..]
+                    .chars()
+                    .next()
+                    .filter(char::is_ascii_alphabetic)
+                    .map(|_| close + 1 + spec_len + 1);
+                let end = conversion_end.unwrap_or(close + 1 + spec_len);
+                if names.iter().any(|name| name.trim().is_empty()) {
+                    errors.push(TemplateError {
+                        span: index..end,
+                        message: "empty field name".to_string(),
+                    });
+                } else if conversion_end.is_none() {
+                    errors.push(TemplateError {
+                        span: index..end,
+                        message: "missing conversion after the field, e.g. %(title)s".to_string(),
+                    });
+                }
+                segments.push((
+                    index..end,
+                    Segment::Field {
+                        names,
+                        default,
+                        conversion: &template[close + 1..end],
+                    },
+                ));
+                index = end;
+                literal_start = end;
+            }
+            b'{' => {
+                flush(&mut segments, literal_start, index);
+                let Some(close) = template[index..].find('}').map(|i| index + i) else {
+                    errors.push(TemplateError {
+                        span: index..template.len(),
+                        message: "{ is not closed".to_string(),
+                    });
+                    return (segments, errors);
+                };
+                let name = &template[index + 1..close];
+                if !APP_FIELDS.iter().any(|(field, _)| *field == name) {
+                    errors.push(TemplateError {
+                        span: index..close + 1,
+                        message: format!(
+                            "unknown field {{{name}}}, available: {{artist}}, {{song}}"
+                        ),
+                    });
+                }
+                segments.push((index..close + 1, Segment::AppField(name)));
+                index = close + 1;
+                literal_start = index;
+            }
+            _ => index += template[index..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    flush(&mut segments, literal_start, template.len());
+    (segments, errors)
+}
+
+// Everything that would make yt-dlp fail or write outside the download folder
+pub fn validate(template: &str) -> Vec<TemplateError> {
+    if template.trim().is_empty() {
+        return vec![TemplateError {
+            span: 0..0,
+            message: "template is empty".to_string(),
+        }];
+    }
+    let (segments, mut errors) = parse(template);
+    // after a syntax error the segment list is incomplete
+    let parsed_completely = errors.is_empty();
+    for (span, segment) in &segments {
+        let text = match segment {
+            Segment::Literal(text) => text,
+            Segment::Field { conversion, .. } => {
+                if pad_width(conversion).is_none_or(|width| width > MAX_NAME_CHARS) {
+                    errors.push(TemplateError {
+                        span: span.clone(),
+                        message: format!("padding is limited to {MAX_NAME_CHARS} characters"),
+                    });
+                }
+                continue;
+            }
+            Segment::AppField(_) => continue,
+        };
+        for (offset, c) in text.char_indices() {
+            if INVALID_CHARS.contains(&c) || c.is_control() {
+                let start = span.start + offset;
+                errors.push(TemplateError {
+                    span: start..start + c.len_utf8(),
+                    message: match c {
+                        '\\' => "use / to separate folders".to_string(),
+                        c if c.is_control() => "control characters are not allowed".to_string(),
+                        c => format!("{c} is not allowed in file names"),
+                    },
+                });
+            }
+        }
+    }
+    if template.starts_with('/') {
+        errors.push(TemplateError {
+            span: 0..1,
+            message: "the template is relative to the download folder".to_string(),
+        });
+    }
+    if template.split('/').any(|part| part == "..") {
+        errors.push(TemplateError {
+            span: 0..template.len(),
+            message: ".. would leave the download folder".to_string(),
+        });
+    }
+    if template.ends_with('/') {
+        errors.push(TemplateError {
+            span: template.len() - 1..template.len(),
+            message: "the template ends with a folder, add a file name".to_string(),
+        });
+    }
+    let has_ext = segments.iter().any(
+        |(_, segment)| matches!(segment, Segment::Field { names, .. } if names.contains(&"ext")),
+    );
+    if parsed_completely && !has_ext {
+        errors.push(TemplateError {
+            span: template.len()..template.len(),
+            message: "end the template with .%(ext)s, otherwise files have no extension"
+                .to_string(),
+        });
+    }
+    errors.sort_by_key(|error| error.span.start);
+    errors
+}
+
+// "Artist - Title" -> ("Artist", "Title")
+pub fn split_artist_title(title: &str) -> Option<(String, String)> {
+    [" - ", " – ", " — "].iter().find_map(|separator| {
+        let (artist, song) = title.split_once(separator)?;
+        let (artist, song) = (artist.trim(), song.trim());
+        (!artist.is_empty() && !song.is_empty()).then(|| (artist.to_string(), song.to_string()))
+    })
+}
+
+// Makes a single path component safe on Windows, macOS and Linux
+pub fn sanitize_filename(name: &str) -> String {
+    let clean: String = name
+        .chars()
+        .map(|c| {
+            if INVALID_CHARS.contains(&c) || c == '/' || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    // Windows dislikes trailing dots and spaces
+    let clean = clean.trim().trim_end_matches(['.', ' ']).to_string();
+    if clean.is_empty() {
+        return "_".to_string();
+    }
+    let stem = clean
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.ends_with(|c: char| c.is_ascii_digit()));
+    if reserved { format!("_{clean}") } else { clean }
+}
+
+// Replaces {artist} and {song} for one job. Without an "Artist - Title" title the
+// fields fall back to what yt-dlp knows (artist/track tags, channel, title).
+// Playlist entries are downloaded one by one, so their playlist fields are filled in here.
+pub fn resolve_app_fields(
+    template: &str,
+    title: &str,
+    playlist: Option<&PlaylistPosition>,
+) -> String {
+    let parsed = split_artist_title(title);
+    let (segments, _) = parse(template);
+    segments
+        .into_iter()
+        .map(|(span, segment)| match (segment, &parsed) {
+            (Segment::AppField("artist"), Some((artist, _))) => escape(&sanitize_filename(artist)),
+            (Segment::AppField("song"), Some((_, song))) => escape(&sanitize_filename(song)),
+            (Segment::AppField("artist"), None) => "%(artist,channel,uploader)s".to_string(),
+            (Segment::AppField("song"), None) => "%(track,title)s".to_string(),
+            (
+                Segment::Field {
+                    names, conversion, ..
+                },
+                _,
+            ) => {
+                let value = playlist
+                    .and_then(|playlist| names.iter().find_map(|name| playlist.field(name.trim())));
+                match value {
+                    Some(value) => escape(&format_value(&value, conversion)),
+                    None => template[span].to_string(),
+                }
+            }
+            _ => template[span].to_string(),
+        })
+        .collect()
+}
+
+fn escape(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+// Known values of one video for the preview
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues {
+    pub title: String,
+    pub id: String,
+    pub channel: String,
+    // YYYYMMDD like yt-dlp
+    pub upload_date: String,
+    pub ext: String,
+    pub playlist_title: Option<String>,
+    pub playlist_index: Option<u32>,
+}
+
+impl TemplateValues {
+    fn get(&self, field: &str) -> Option<String> {
+        let value = match field {
+            "title" | "fulltitle" => self.title.clone(),
+            "id" => self.id.clone(),
+            "channel" | "uploader" => self.channel.clone(),
+            "upload_date" => self.upload_date.clone(),
+            "ext" => self.ext.clone(),
+            "playlist_title" | "playlist" => self.playlist_title.clone()?,
+            "playlist_index" => self.playlist_index?.to_string(),
+            _ => return None,
+        };
+        (!value.is_empty()).then_some(value)
+    }
+}
+
+// Renders the template roughly like yt-dlp would; unknown fields become "NA"
+pub fn render_preview(template: &str, values: &TemplateValues) -> String {
+    let template = resolve_app_fields(template, &values.title, None);
+    let (segments, _) = parse(&template);
+    let mut path = String::new();
+    for (_, segment) in segments {
+        match segment {
+            Segment::Literal(text) => path.push_str(&text.replace("%%", "%")),
+            Segment::Field {
+                names,
+                default,
+                conversion,
+            } => {
+                let value = names.iter().find_map(|name| values.get(name.trim()));
+                path.push_str(&match value {
+                    Some(value) => format_value(&value, conversion),
+                    None => default.unwrap_or("NA").to_string(),
+                });
+            }
+            Segment::AppField(name) => path.push_str(name),
+        }
+    }
+    path
+}
+
+fn format_value(value: &str, conversion: &str) -> String {
+    if conversion.ends_with('d')
+        && let Ok(number) = value.parse::<i64>()
+    {
+        // 03d -> pad to 3 digits with zeros; the preview runs while typing, so a huge
+        // width must not allocate
+        let width = pad_width(conversion).unwrap_or(0).min(MAX_NAME_CHARS);
+        if conversion.starts_with('0') {
+            return format!("{number:0width$}");
+        }
+        return format!("{number:width$}");
+    }
+    sanitize_filename(value)
+}
+
+// "03d" -> 3, "-10s" -> 10, "s" -> 0; None if the width doesn't even fit a usize
+fn pad_width(conversion: &str) -> Option<usize> {
+    let rest = conversion.trim_start_matches(['0', '-', '+', '#', ' ']);
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    if digits == 0 {
+        return Some(0);
+    }
+    rest[..digits].parse().ok()
+}
+
+// Expands ~ and %VAR% like yt-dlp does for -P
+pub fn expand_path(path: &str) -> PathBuf {
+    let mut expanded = String::new();
+    let mut rest = path;
+    if let Some(stripped) = rest.strip_prefix('~')
+        && let Some(dirs) = directories::BaseDirs::new()
+    {
+        expanded.push_str(&dirs.home_dir().to_string_lossy());
+        rest = stripped;
+    }
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            break;
+        };
+        let name = &rest[start + 1..start + 1 + len];
+        expanded.push_str(&rest[..start]);
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => expanded.push_str(&value),
+            _ => expanded.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    expanded.push_str(rest);
+    PathBuf::from(expanded)
+}
+
+// "song.mp3" -> "song (1).mp3", "song (2).mp3", ... until the name is free
+pub fn unique_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{stem} ({n}){extension}")))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_default()
+}
+
+// Moves everything yt-dlp wrote into `from` to the same relative place under `to`.
+// Existing files are kept, new ones get a suffix. Returns the final paths.
+pub fn move_into_place(from: &Path, to: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut moved = Vec::new();
+    // nothing written, e.g. because the video was already in the archive
+    if !from.exists() {
+        return Ok(moved);
+    }
+    let mut pending = vec![from.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let relative = path.strip_prefix(from).unwrap_or(&path);
+            let target = unique_path(&to.join(relative));
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::rename(&path, &target)?;
+            moved.push(target);
+        }
+    }
+    std::fs::remove_dir_all(from)?;
+    moved.sort();
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(template: &str) -> Vec<String> {
+        validate(template)
+            .into_iter()
+            .map(|error| error.message)
+            .collect()
+    }
+
+    #[test]
+    fn accepts_yt_dlp_and_app_fields() {
+        assert!(validate(DEFAULT_OUTPUT_TEMPLATE).is_empty());
+        assert!(
+            validate("%(channel)s/%(upload_date)s - {artist} - {song} [%(id)s].%(ext)s").is_empty()
+        );
+        assert!(
+            validate("%(playlist_title,channel)s/%(playlist_index)03d %(title)s.%(ext)s")
+                .is_empty()
+        );
+        assert!(validate("100%% %(title)s.%(ext)s").is_empty());
+    }
+
+    #[test]
+    fn reports_invalid_templates() {
+        assert_eq!(messages(""), ["template is empty"]);
+        assert_eq!(
+            messages("%(title)s: live?.%(ext)s"),
+            [
+                ": is not allowed in file names",
+                "? is not allowed in file names"
+            ]
+        );
+        let errors = validate("%(title)s: x.%(ext)s");
+        assert_eq!(errors[0].span, 9..10);
+        assert_eq!(
+            messages("%(title.%(ext)s"),
+            ["field is not closed, use %(name)s"]
+        );
+        assert_eq!(
+            messages("%(title).%(ext)s"),
+            ["missing conversion after the field, e.g. %(title)s"]
+        );
+        assert_eq!(
+            messages("../%(title)s.%(ext)s"),
+            [".. would leave the download folder"]
+        );
+        assert_eq!(
+            messages("{artist}/{album}.%(ext)s"),
+            ["unknown field {album}, available: {artist}, {song}"]
+        );
+        assert_eq!(
+            messages("%(title)s"),
+            ["end the template with .%(ext)s, otherwise files have no extension"]
+        );
+        for template in [
+            "%(upload_date)0999999999999d.%(ext)s",
+            "%(title)99999999999999999999999s.%(ext)s",
+        ] {
+            assert_eq!(messages(template), ["padding is limited to 180 characters"]);
+        }
+        assert!(validate("%(playlist_index)03d.%(ext)s").is_empty());
+    }
+
+    #[test]
+    fn renders_the_preview() {
+        let values = TemplateValues {
+            title: "Daft Punk - One More Time".to_string(),
+            id: "abc".to_string(),
+            channel: "Daft Punk".to_string(),
+            upload_date: "20091002".to_string(),
+            ext: "m4a".to_string(),
+            playlist_title: None,
+            playlist_index: Some(7),
+        };
+        assert_eq!(
+            render_preview(
+                "{artist}/%(playlist_index)03d {song} [%(id)s].%(ext)s",
+                &values
+            ),
+            "Daft Punk/007 One More Time [abc].m4a"
+        );
+        assert_eq!(
+            render_preview("%(playlist_title,channel)s/%(title)s.%(ext)s", &values),
+            "Daft Punk/Daft Punk - One More Time.m4a"
+        );
+        assert_eq!(render_preview("%(album)s.%(ext)s", &values), "NA.m4a");
+        assert_eq!(
+            render_preview("%(album|Singles)s.%(ext)s", &values),
+            "Singles.m4a"
+        );
+        let padded = render_preview("%(upload_date)0999999999999d.%(ext)s", &values);
+        assert_eq!(padded.len(), MAX_NAME_CHARS + ".m4a".len());
+        assert!(padded.ends_with("20091002.m4a"));
+    }
+
+    #[test]
+    fn app_fields_fall_back_to_yt_dlp_fields() {
+        assert_eq!(
+            resolve_app_fields("{artist} - {song}.%(ext)s", "AC/DC - 100% Live", None),
+            "AC_DC - 100%% Live.%(ext)s"
+        );
+        assert_eq!(
+            resolve_app_fields("{artist} - {song}.%(ext)s", "Just a title", None),
+            "%(artist,channel,uploader)s - %(track,title)s.%(ext)s"
+        );
+    }
+
+    #[test]
+    fn playlist_jobs_get_their_index() {
+        let position = PlaylistPosition {
+            playlist_id: "PL1".to_string(),
+            title: "Best of 100%".to_string(),
+            index: 7,
+            count: 12,
+        };
+        assert_eq!(
+            resolve_app_fields(
+                "%(playlist_title)s/%(playlist_index)02d - %(title)s.%(ext)s",
+                "Song",
+                Some(&position)
+            ),
+            "Best of 100%%/07 - %(title)s.%(ext)s"
+        );
+        // single videos let yt-dlp fill the fields
+        assert_eq!(
+            resolve_app_fields("%(playlist_index)s.%(ext)s", "Song", None),
+            "%(playlist_index)s.%(ext)s"
+        );
+    }
+
+    #[test]
+    fn sanitizes_file_names() {
+        assert_eq!(
+            sanitize_filename("What? Yes: no/maybe"),
+            "What_ Yes_ no_maybe"
+        );
+        assert_eq!(sanitize_filename("  trailing dots... "), "trailing dots");
+        assert_eq!(sanitize_filename("CON"), "_CON");
+        assert_eq!(sanitize_filename("com1.txt"), "_com1.txt");
+        assert_eq!(sanitize_filename("Console"), "Console");
+        assert_eq!(sanitize_filename("..."), "_");
+    }
+
+    #[test]
+    fn moves_files_without_overwriting() {
+        let root = std::env::temp_dir().join(format!("ytgui-template-{}", std::process::id()));
+        let partial = root.join(PARTIAL_DIR).join("job-1");
+        std::fs::create_dir_all(partial.join("Artist")).unwrap();
+        std::fs::create_dir_all(root.join("Artist")).unwrap();
+        std::fs::write(root.join("Artist/song.mp3"), "old").unwrap();
+        std::fs::write(partial.join("Artist/song.mp3"), "new").unwrap();
+
+        let moved = move_into_place(&partial, &root).unwrap();
+        assert_eq!(moved, [root.join("Artist/song (1).mp3")]);
+        assert_eq!(
+            std::fs::read_to_string(root.join("Artist/song.mp3")).unwrap(),
+            "old"
+        );
+        assert!(!partial.exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+use crate::metadata::PlaylistEntry;
+use std::ops::RangeInclusive;
+
+// Where a child job sits in its playlist; fills %(playlist_index)s and friends in the
+// output template, because yt-dlp only sees the single watch URL of the entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistPosition {
+    pub playlist_id: String,
+    pub title: String,
+    // 1-based like yt-dlp
+    pub index: usize,
+    pub count: usize,
+}
+
+impl PlaylistPosition {
+    pub fn field(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "playlist_index" => self.index.to_string(),
+            "playlist_count" | "n_entries" => self.count.to_string(),
+            "playlist_title" | "playlist" => self.title.clone(),
+            "playlist_id" => self.playlist_id.clone(),
+            _ => return None,
+        })
+    }
+}
+
+// "1-5, 8, 20-" -> [1..=5, 8..=8, 20..=MAX]; empty text selects everything
+pub fn parse_ranges(text: &str) -> Result<Vec<RangeInclusive<usize>>, String> {
+    let mut ranges = Vec::new();
+    for part in text
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let number = |value: &str| -> Result<usize, String> {
+            match value.trim().parse::<usize>() {
+                Ok(0) => Err("entries are counted from 1".to_string()),
+                Ok(number) => Ok(number),
+                Err(_) => Err(format!("\"{part}\" is not a number or range like 3-7")),
+            }
+        };
+        let range = match part.split_once('-') {
+            Some((start, "")) => number(start)?..=usize::MAX,
+            Some(("", end)) => 1..=number(end)?,
+            Some((start, end)) => number(start)?..=number(end)?,
+            None => {
+                let index = number(part)?;
+                index..=index
+            }
+        };
+        if range.is_empty() {
+            return Err(format!("\"{part}\" ends before it starts"));
+        }
+        ranges.push(range);
+    }
+    Ok(ranges)
+}
+
+// Entries in the requested ranges with their 1-based index; archived ones are counted
+// separately so the UI can say how many were skipped
+pub fn select_entries<'a>(
+    entries: &'a [PlaylistEntry],
+    ranges: &[RangeInclusive<usize>],
+    is_archived: impl Fn(&str) -> bool,
+) -> (Vec<(usize, &'a PlaylistEntry)>, usize) {
+    let mut selected = Vec::new();
+    let mut skipped = 0;
+    for (position, entry) in entries.iter().enumerate() {
+        let index = position + 1;
+        if !ranges.is_empty() && !ranges.iter().any(|range| range.contains(&index)) {
+            continue;
+        }
+        if is_archived(&entry.id) {
+            skipped += 1;
+        } else {
+            selected.push((index, entry));
+        }
+    }
+    (selected, skipped)
+}
+
+// Playlist id from a pasted link like https://www.youtube.com/playlist?list=PL...
+pub fn playlist_id_from_url(text: &str) -> Option<String> {
+    let text = text.trim();
+    if !text.starts_with("http") || text.contains(char::is_whitespace) {
+        return None;
+    }
+    let query = text.split_once('?')?.1;
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("list="))
+        .map(|id| id.split('#').next().unwrap_or(id).to_string())
+        .filter(|id| !id.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(count: usize) -> Vec<PlaylistEntry> {
+        (1..=count)
+            .map(|i| PlaylistEntry {
+                id: format!("v{i}"),
+                title: format!("Track {i}"),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_entry_ranges() {
+        assert_eq!(parse_ranges(""), Ok(vec![]));
+        assert_eq!(
+            parse_ranges("1-3, 5,9-"),
+            Ok(vec![1..=3, 5..=5, 9..=usize::MAX])
+        );
+        assert_eq!(parse_ranges("-2"), Ok(vec![1..=2]));
+        assert!(parse_ranges("0").is_err());
+        assert!(parse_ranges("5-2").is_err());
+        assert!(parse_ranges("a-b").is_err());
+    }
+
+    #[test]
+    fn selects_ranges_and_skips_archived_entries() {
+        let entries = entries(6);
+        let ranges = parse_ranges("2-4,6").unwrap();
+        let (selected, skipped) = select_entries(&entries, &ranges, |id| id == "v3");
+        let picked: Vec<(usize, &str)> = selected
+            .iter()
+            .map(|(index, entry)| (*index, entry.id.as_str()))
+            .collect();
+        assert_eq!(picked, [(2, "v2"), (4, "v4"), (6, "v6")]);
+        assert_eq!(skipped, 1);
+
+        let (selected, skipped) = select_entries(&entries, &[], |_| false);
+        assert_eq!(selected.len(), 6);
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn finds_playlist_ids_in_links() {
+        assert_eq!(
+            playlist_id_from_url("https://www.youtube.com/playlist?list=PL123abc"),
+            Some("PL123abc".to_string())
+        );
+        assert_eq!(
+            playlist_id_from_url("https://www.youtube.com/watch?v=abc&list=PLxyz&index=3"),
+            Some("PLxyz".to_string())
+        );
+        assert_eq!(playlist_id_from_url("lofi list=PL1"), None);
+        assert_eq!(
+            playlist_id_from_url("https://www.youtube.com/watch?v=abc"),
+            None
+        );
+    }
+}
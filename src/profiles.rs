@@ -0,0 +1,585 @@
+use serde::{Deserialize, Serialize};
+
+// Audio codec passed to yt-dlp --audio-format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum AudioCodec {
+    Aac,
+    // AAC in an MP4 container, which unlike raw .aac can hold tags
+    #[default]
+    M4a,
+    Mp3,
+    Opus,
+    Vorbis,
+    Flac,
+    Wav,
+    // keep whatever the best audio stream is, no re-encoding
+    Best,
+}
+
+impl AudioCodec {
+    pub const ALL: [AudioCodec; 8] = [
+        AudioCodec::Aac,
+        AudioCodec::M4a,
+        AudioCodec::Mp3,
+        AudioCodec::Opus,
+        AudioCodec::Vorbis,
+        AudioCodec::Flac,
+        AudioCodec::Wav,
+        AudioCodec::Best,
+    ];
+
+    pub fn api_value(&self) -> &'static str {
+        match self {
+            AudioCodec::Aac => "aac",
+            AudioCodec::M4a => "m4a",
+            AudioCodec::Mp3 => "mp3",
+            AudioCodec::Opus => "opus",
+            AudioCodec::Vorbis => "vorbis",
+            AudioCodec::Flac => "flac",
+            AudioCodec::Wav => "wav",
+            AudioCodec::Best => "best",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AudioCodec::Aac => "AAC",
+            AudioCodec::M4a => "M4A (AAC)",
+            AudioCodec::Mp3 => "MP3",
+            AudioCodec::Opus => "Opus",
+            AudioCodec::Vorbis => "Vorbis",
+            AudioCodec::Flac => "FLAC",
+            AudioCodec::Wav => "WAV",
+            AudioCodec::Best => "Original",
+        }
+    }
+
+    // File extension yt-dlp gives the extracted audio
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioCodec::Aac => "aac",
+            AudioCodec::M4a | AudioCodec::Best => "m4a",
+            AudioCodec::Mp3 => "mp3",
+            AudioCodec::Opus => "opus",
+            AudioCodec::Vorbis => "ogg",
+            AudioCodec::Flac => "flac",
+            AudioCodec::Wav => "wav",
+        }
+    }
+
+    // Lossless and untouched audio has no bitrate setting
+    pub fn has_bitrate(&self) -> bool {
+        !matches!(self, AudioCodec::Flac | AudioCodec::Wav | AudioCodec::Best)
+    }
+
+    // Containers yt-dlp can embed cover art into; raw .aac and .wav have none
+    pub fn supports_thumbnail(&self) -> bool {
+        !matches!(self, AudioCodec::Aac | AudioCodec::Wav)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum DownloadMode {
+    // extract the audio track (-x)
+    #[default]
+    Audio,
+    // best video up to the height cap, merged with the best audio
+    Video,
+}
+
+// Preferred video codec; yt-dlp falls back to others if it is not offered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum VideoCodec {
+    #[default]
+    Any,
+    H264,
+    Vp9,
+    Av1,
+}
+
+impl VideoCodec {
+    pub const ALL: [VideoCodec; 4] = [
+        VideoCodec::Any,
+        VideoCodec::H264,
+        VideoCodec::Vp9,
+        VideoCodec::Av1,
+    ];
+
+    // value for yt-dlp -S vcodec:...
+    pub fn api_value(&self) -> Option<&'static str> {
+        match self {
+            VideoCodec::Any => None,
+            VideoCodec::H264 => Some("h264"),
+            VideoCodec::Vp9 => Some("vp9"),
+            VideoCodec::Av1 => Some("av01"),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            VideoCodec::Any => "any codec",
+            VideoCodec::H264 => "H.264",
+            VideoCodec::Vp9 => "VP9",
+            VideoCodec::Av1 => "AV1",
+        }
+    }
+
+    // WebM only takes VP8/VP9/AV1; MP4 and MKV take all of them
+    pub fn fits(&self, container: VideoContainer) -> bool {
+        !(*self == VideoCodec::H264 && container == VideoContainer::Webm)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum VideoContainer {
+    #[default]
+    Mp4,
+    Mkv,
+    Webm,
+}
+
+impl VideoContainer {
+    pub const ALL: [VideoContainer; 3] = [
+        VideoContainer::Mp4,
+        VideoContainer::Mkv,
+        VideoContainer::Webm,
+    ];
+
+    pub fn api_value(&self) -> &'static str {
+        match self {
+            VideoContainer::Mp4 => "mp4",
+            VideoContainer::Mkv => "mkv",
+            VideoContainer::Webm => "webm",
+        }
+    }
+
+    // yt-dlp cannot embed cover art into webm
+    pub fn supports_thumbnail(&self) -> bool {
+        *self != VideoContainer::Webm
+    }
+
+    // Format filters for the video and the audio stream; ffmpeg can't merge H.264 or AAC
+    // into WebM, and YouTube's best audio is often AAC
+    fn stream_filters(&self) -> (&'static str, &'static str) {
+        match self {
+            VideoContainer::Webm => ("[vcodec~='^(vp0?[89]|av01)']", "[acodec~='^(opus|vorbis)']"),
+            _ => ("", ""),
+        }
+    }
+}
+
+pub const VIDEO_HEIGHTS: [u32; 6] = [2160, 1440, 1080, 720, 480, 360];
+
+// Named set of yt-dlp options, audio extraction or video. Picked per click or the default from the settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadProfile {
+    pub name: String,
+    pub mode: DownloadMode,
+    pub codec: AudioCodec,
+    // kbit/s for --audio-quality; None = best VBR quality
+    pub bitrate_kbps: Option<u32>,
+    // Hz; None keeps the sample rate of the source
+    pub sample_rate: Option<u32>,
+    pub embed_thumbnail: bool,
+    pub embed_metadata: bool,
+    // video mode: highest allowed resolution, None = best available
+    pub max_height: Option<u32>,
+    pub video_codec: VideoCodec,
+    pub container: VideoContainer,
+    // exact yt-dlp format picked in the format inspector; only set on single jobs
+    #[serde(skip)]
+    pub format_id: Option<String>,
+}
+
+impl Default for DownloadProfile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            mode: DownloadMode::default(),
+            codec: AudioCodec::default(),
+            bitrate_kbps: None,
+            sample_rate: None,
+            embed_thumbnail: true,
+            embed_metadata: true,
+            max_height: None,
+            video_codec: VideoCodec::default(),
+            container: VideoContainer::default(),
+            format_id: None,
+        }
+    }
+}
+
+pub const SAMPLE_RATES: [u32; 3] = [44_100, 48_000, 96_000];
+
+impl DownloadProfile {
+    // Profiles a fresh install starts with; the first one is the default
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self {
+                name: "M4A".to_string(),
+                ..Default::default()
+            },
+            Self {
+                name: "Phone MP3 192k".to_string(),
+                codec: AudioCodec::Mp3,
+                bitrate_kbps: Some(192),
+                sample_rate: Some(44_100),
+                ..Default::default()
+            },
+            Self {
+                name: "Opus 128k".to_string(),
+                codec: AudioCodec::Opus,
+                bitrate_kbps: Some(128),
+                ..Default::default()
+            },
+            Self {
+                name: "Archive FLAC".to_string(),
+                codec: AudioCodec::Flac,
+                ..Default::default()
+            },
+            Self {
+                name: "Video MP4 1080p".to_string(),
+                mode: DownloadMode::Video,
+                max_height: Some(1080),
+                video_codec: VideoCodec::H264,
+                ..Default::default()
+            },
+            Self {
+                name: "Video MKV best".to_string(),
+                mode: DownloadMode::Video,
+                container: VideoContainer::Mkv,
+                ..Default::default()
+            },
+        ]
+    }
+
+    // Format and post-processing arguments for yt-dlp
+    pub fn yt_dlp_args(&self) -> Vec<String> {
+        match self.mode {
+            DownloadMode::Audio => self.audio_args(),
+            DownloadMode::Video => self.video_args(),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self.mode {
+            DownloadMode::Audio => self.codec.extension(),
+            DownloadMode::Video => self.container.api_value(),
+        }
+    }
+
+    pub fn supports_thumbnail(&self) -> bool {
+        match self.mode {
+            DownloadMode::Audio => self.codec.supports_thumbnail(),
+            DownloadMode::Video => self.container.supports_thumbnail(),
+        }
+    }
+
+    fn audio_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(format_id) = &self.format_id {
+            args.push("-f".to_string());
+            args.push(format_id.clone());
+        }
+        args.extend([
+            "-x".to_string(),
+            "--audio-format".to_string(),
+            self.codec.api_value().to_string(),
+        ]);
+        if self.codec.has_bitrate() {
+            args.push("--audio-quality".to_string());
+            args.push(match self.bitrate_kbps {
+                Some(kbps) => format!("{kbps}K"),
+                None => "0".to_string(),
+            });
+        }
+        if let Some(rate) = self.sample_rate {
+            args.push("--postprocessor-args".to_string());
+            args.push(format!("ExtractAudio:-ar {rate}"));
+        }
+        self.push_embed_args(&mut args);
+        args
+    }
+
+    // Best video within the height cap plus the best audio, merged into the container.
+    // Without separate streams the best single file within the cap is taken.
+    fn video_args(&self) -> Vec<String> {
+        let format = match &self.format_id {
+            Some(format_id) => format_id.clone(),
+            None => {
+                let height = self
+                    .max_height
+                    .map(|height| format!("[height<={height}]"))
+                    .unwrap_or_default();
+                let (video, audio) = self.container.stream_filters();
+                format!("bv*{height}{video}+ba{audio}/b{height}{video}{audio}")
+            }
+        };
+        let mut args = vec!["-f".to_string(), format];
+        if self.format_id.is_none()
+            && let Some(codec) = self.video_codec.api_value()
+        {
+            args.push("-S".to_string());
+            args.push(format!("vcodec:{codec}"));
+        }
+        args.push("--merge-output-format".to_string());
+        args.push(self.container.api_value().to_string());
+        self.push_embed_args(&mut args);
+        args
+    }
+
+    // Combinations yt-dlp would only fail on after the download
+    pub fn validate(&self) -> Result<(), String> {
+        if self.mode == DownloadMode::Video && !self.video_codec.fits(self.container) {
+            return Err(format!(
+                "{} does not fit into WebM, pick MP4 or MKV",
+                self.video_codec.label()
+            ));
+        }
+        Ok(())
+    }
+
+    fn push_embed_args(&self, args: &mut Vec<String>) {
+        if self.embed_metadata {
+            args.push("--embed-metadata".to_string());
+        }
+        if self.embed_thumbnail && self.supports_thumbnail() {
+            args.push("--embed-thumbnail".to_string());
+        }
+    }
+
+    // "MP3 · 192 kbit/s · 44.1 kHz"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(format_id) = &self.format_id {
+            parts.push(format!("format {format_id}"));
+        }
+        match self.mode {
+            DownloadMode::Video => {
+                parts.push("Video".to_string());
+                parts.push(self.container.api_value().to_uppercase());
+                if self.format_id.is_none() {
+                    parts.push(match self.max_height {
+                        Some(height) => format!("≤{height}p"),
+                        None => "best quality".to_string(),
+                    });
+                    parts.push(self.video_codec.label().to_string());
+                }
+            }
+            DownloadMode::Audio => {
+                parts.push(self.codec.label().to_string());
+                if self.codec.has_bitrate() {
+                    parts.push(match self.bitrate_kbps {
+                        Some(kbps) => format!("{kbps} kbit/s"),
+                        None => "best VBR".to_string(),
+                    });
+                }
+                if let Some(rate) = self.sample_rate {
+                    parts.push(format!("{} kHz", rate as f32 / 1000.0));
+                }
+            }
+        }
+        parts.join(" · ")
+    }
+}
+
+pub fn export_profiles(profiles: &[DownloadProfile]) -> String {
+    serde_json::to_string_pretty(profiles).unwrap_or_default()
+}
+
+// Imported profiles replace existing ones with the same name, new names are appended.
+// Returns how many profiles were read.
+pub fn import_profiles(profiles: &mut Vec<DownloadProfile>, json: &str) -> Result<usize, String> {
+    let imported: Vec<DownloadProfile> =
+        serde_json::from_str(json).map_err(|e| format!("Not a profile list: {e}"))?;
+    if let Some(unnamed) = imported.iter().find(|p| p.name.trim().is_empty()) {
+        return Err(format!(
+            "Every profile needs a name ({} without one)",
+            unnamed.codec.label()
+        ));
+    }
+    for profile in &imported {
+        profile
+            .validate()
+            .map_err(|message| format!("{}: {message}", profile.name))?;
+    }
+    let count = imported.len();
+    for profile in imported {
+        match profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => profiles.push(profile),
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str) -> DownloadProfile {
+        DownloadProfile::defaults()
+            .into_iter()
+            .find(|p| p.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn builds_yt_dlp_arguments() {
+        assert_eq!(
+            profile("Phone MP3 192k").yt_dlp_args(),
+            [
+                "-x",
+                "--audio-format",
+                "mp3",
+                "--audio-quality",
+                "192K",
+                "--postprocessor-args",
+                "ExtractAudio:-ar 44100",
+                "--embed-metadata",
+                "--embed-thumbnail",
+            ]
+        );
+        // FLAC has no bitrate
+        assert_eq!(
+            profile("Archive FLAC").yt_dlp_args(),
+            [
+                "-x",
+                "--audio-format",
+                "flac",
+                "--embed-metadata",
+                "--embed-thumbnail"
+            ]
+        );
+        // raw AAC cannot embed a cover
+        assert!(
+            !DownloadProfile {
+                codec: AudioCodec::Aac,
+                ..Default::default()
+            }
+            .yt_dlp_args()
+            .contains(&"--embed-thumbnail".to_string())
+        );
+    }
+
+    #[test]
+    fn video_mode_merges_streams_into_the_container() {
+        assert_eq!(
+            profile("Video MP4 1080p").yt_dlp_args(),
+            [
+                "-f",
+                "bv*[height<=1080]+ba/b[height<=1080]",
+                "-S",
+                "vcodec:h264",
+                "--merge-output-format",
+                "mp4",
+                "--embed-metadata",
+                "--embed-thumbnail",
+            ]
+        );
+        let webm = DownloadProfile {
+            mode: DownloadMode::Video,
+            container: VideoContainer::Webm,
+            ..Default::default()
+        };
+        let args = webm.yt_dlp_args();
+        assert_eq!(
+            args[..2],
+            [
+                "-f",
+                "bv*[vcodec~='^(vp0?[89]|av01)']+ba[acodec~='^(opus|vorbis)']\
+                 /b[vcodec~='^(vp0?[89]|av01)'][acodec~='^(opus|vorbis)']"
+            ]
+        );
+        assert!(!args.contains(&"-x".to_string()));
+        assert!(!args.contains(&"--embed-thumbnail".to_string()));
+    }
+
+    #[test]
+    fn rejects_codecs_the_container_cannot_hold() {
+        let mut video = DownloadProfile {
+            name: "Web".to_string(),
+            mode: DownloadMode::Video,
+            video_codec: VideoCodec::H264,
+            container: VideoContainer::Webm,
+            ..Default::default()
+        };
+        assert!(video.validate().is_err());
+        let json = export_profiles(std::slice::from_ref(&video));
+        assert!(import_profiles(&mut Vec::new(), &json).is_err());
+
+        for container in [VideoContainer::Mp4, VideoContainer::Mkv] {
+            video.container = container;
+            assert!(video.validate().is_ok());
+        }
+        video.container = VideoContainer::Webm;
+        for codec in [VideoCodec::Any, VideoCodec::Vp9, VideoCodec::Av1] {
+            video.video_codec = codec;
+            assert!(video.validate().is_ok());
+        }
+        // audio profiles ignore the video settings
+        video.video_codec = VideoCodec::H264;
+        video.mode = DownloadMode::Audio;
+        assert!(video.validate().is_ok());
+    }
+
+    #[test]
+    fn picked_format_replaces_the_selection() {
+        let mut video = profile("Video MP4 1080p");
+        video.format_id = Some("137+ba".to_string());
+        let args = video.yt_dlp_args();
+        assert_eq!(args[..2], ["-f", "137+ba"]);
+        assert!(!args.contains(&"-S".to_string()));
+        assert_eq!(video.summary(), "format 137+ba · Video · MP4");
+
+        let mut audio = profile("M4A");
+        audio.format_id = Some("251".to_string());
+        assert_eq!(audio.yt_dlp_args()[..3], ["-f", "251", "-x"]);
+    }
+
+    #[test]
+    fn summary_describes_the_profile() {
+        assert_eq!(
+            profile("Phone MP3 192k").summary(),
+            "MP3 · 192 kbit/s · 44.1 kHz"
+        );
+        assert_eq!(profile("M4A").summary(), "M4A (AAC) · best VBR");
+        assert_eq!(
+            profile("Video MP4 1080p").summary(),
+            "Video · MP4 · ≤1080p · H.264"
+        );
+    }
+
+    #[test]
+    fn export_and_import_round_trip() {
+        let json = export_profiles(&DownloadProfile::defaults());
+        let mut profiles = Vec::new();
+        assert_eq!(import_profiles(&mut profiles, &json), Ok(6));
+        assert_eq!(profiles, DownloadProfile::defaults());
+    }
+
+    #[test]
+    fn import_replaces_profiles_by_name() {
+        let mut profiles = DownloadProfile::defaults();
+        let json = r#"[
+            {"name": "Opus 128k", "codec": "Opus", "bitrate_kbps": 160},
+            {"name": "Car", "codec": "Mp3"}
+        ]"#;
+        assert_eq!(import_profiles(&mut profiles, json), Ok(2));
+        assert_eq!(profiles.len(), 7);
+        assert_eq!(
+            profile_named(&profiles, "Opus 128k").bitrate_kbps,
+            Some(160)
+        );
+        // missing fields get the defaults
+        assert!(profile_named(&profiles, "Car").embed_metadata);
+
+        assert!(import_profiles(&mut profiles, "{}").is_err());
+        assert!(import_profiles(&mut profiles, r#"[{"codec": "Mp3"}]"#).is_err());
+    }
+
+    fn profile_named<'a>(profiles: &'a [DownloadProfile], name: &str) -> &'a DownloadProfile {
+        profiles.iter().find(|p| p.name == name).unwrap()
+    }
+}
@@ -0,0 +1,131 @@
+use crate::share::{DownloadPhase, DownloadProgress};
+use serde::Deserialize;
+use std::time::Duration;
+
+// Progress templates for yt-dlp. The prefix tells download and post-processing lines apart,
+// only the needed fields are printed (the full progress dict contains the whole info_dict).
+pub const DOWNLOAD_PROGRESS_TEMPLATE: &str = "download:[download] %(progress.{status,downloaded_bytes,total_bytes,total_bytes_estimate,speed,eta})j";
+pub const POSTPROCESS_PROGRESS_TEMPLATE: &str =
+    "postprocess:[postprocess] %(progress.{status,postprocessor})j";
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RawProgress {
+    status: String,
+    downloaded_bytes: Option<f64>,
+    total_bytes: Option<f64>,
+    total_bytes_estimate: Option<f64>,
+    speed: Option<f64>,
+    eta: Option<f64>,
+    postprocessor: Option<String>,
+}
+
+// Parses one stdout line printed with the templates above; other output returns None
+pub fn parse_progress_line(line: &str) -> Option<DownloadProgress> {
+    let line = line.trim();
+    if let Some(json) = line.strip_prefix("[download]") {
+        let raw: RawProgress = serde_json::from_str(json.trim()).ok()?;
+        let total_bytes = raw.total_bytes.or(raw.total_bytes_estimate);
+        let percent = match (raw.downloaded_bytes, total_bytes) {
+            _ if raw.status == "finished" => 100.0,
+            (Some(done), Some(total)) if total > 0.0 => (done / total * 100.0).clamp(0.0, 100.0),
+            _ => 0.0,
+        };
+        Some(DownloadProgress {
+            phase: DownloadPhase::Downloading,
+            percent: percent as f32,
+            downloaded_bytes: raw.downloaded_bytes.map(|b| b as u64),
+            total_bytes: total_bytes.map(|b| b as u64),
+            speed: raw.speed,
+            eta: raw
+                .eta
+                .filter(|eta| *eta >= 0.0)
+                .map(Duration::from_secs_f64),
+        })
+    } else if let Some(json) = line.strip_prefix("[postprocess]") {
+        let raw: RawProgress = serde_json::from_str(json.trim()).ok()?;
+        Some(DownloadProgress {
+            phase: DownloadPhase::PostProcessing(raw.postprocessor.unwrap_or_default()),
+            percent: 100.0,
+            ..Default::default()
+        })
+    } else {
+        None
+    }
+}
+
+// 1536 -> "1.5 KiB"
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes.max(0.0);
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{value:.0} {}", UNITS[unit])
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_download_lines() {
+        let line = r#"[download] {"status": "downloading", "downloaded_bytes": 1048576, "total_bytes": 4194304, "speed": 524288.5, "eta": 6}"#;
+        let progress = parse_progress_line(line).unwrap();
+        assert_eq!(progress.phase, DownloadPhase::Downloading);
+        assert_eq!(progress.percent, 25.0);
+        assert_eq!(progress.downloaded_bytes, Some(1_048_576));
+        assert_eq!(progress.total_bytes, Some(4_194_304));
+        assert_eq!(progress.speed, Some(524_288.5));
+        assert_eq!(progress.eta, Some(Duration::from_secs(6)));
+    }
+
+    #[test]
+    fn falls_back_to_the_size_estimate() {
+        let line = r#"[download] {"status": "downloading", "downloaded_bytes": 50, "total_bytes_estimate": 200.0, "speed": null, "eta": null}"#;
+        let progress = parse_progress_line(line).unwrap();
+        assert_eq!(progress.percent, 25.0);
+        assert_eq!(progress.total_bytes, Some(200));
+        assert_eq!(progress.speed, None);
+        assert_eq!(progress.eta, None);
+
+        // without a size it stays at 0 until yt-dlp reports "finished"
+        let line = r#"[download] {"status": "downloading", "downloaded_bytes": 50}"#;
+        assert_eq!(parse_progress_line(line).unwrap().percent, 0.0);
+        let line = r#"[download] {"status": "finished", "downloaded_bytes": 50}"#;
+        assert_eq!(parse_progress_line(line).unwrap().percent, 100.0);
+    }
+
+    #[test]
+    fn parses_postprocess_lines() {
+        let line = r#"[postprocess] {"status": "started", "postprocessor": "ExtractAudio"}"#;
+        let progress = parse_progress_line(line).unwrap();
+        assert_eq!(
+            progress.phase,
+            DownloadPhase::PostProcessing("ExtractAudio".to_string())
+        );
+    }
+
+    #[test]
+    fn ignores_other_output() {
+        assert_eq!(
+            parse_progress_line("[youtube] abc: Downloading webpage"),
+            None
+        );
+        assert_eq!(parse_progress_line("[download] Destination: a.m4a"), None);
+        assert_eq!(parse_progress_line(""), None);
+    }
+
+    #[test]
+    fn formats_byte_sizes() {
+        assert_eq!(format_bytes(512.0), "512 B");
+        assert_eq!(format_bytes(1536.0), "1.5 KiB");
+        assert_eq!(format_bytes(5.0 * 1024.0 * 1024.0), "5.0 MiB");
+    }
+}
@@ -0,0 +1,389 @@
+use crate::share::{
+    SafeSearch, SearchOrder, SearchQuery, SearchType, VideoDurationFilter, to_rfc3339,
+};
+use std::ops::Range;
+use std::time::Duration;
+
+// Search box mini-language, e.g.
+//   lofi dur:>10m after:2024-01 channel:"Chillhop" order:views
+// Known operators are removed from the text and mapped onto the query,
+// everything else stays part of q.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    // Byte range in the search text, used for the inline highlighting
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct ParsedQuery {
+    pub query: SearchQuery,
+    pub errors: Vec<QueryError>,
+}
+
+// Parses `input` on top of `base` (the settings/filter panel values)
+pub fn parse_query(input: &str, base: SearchQuery) -> ParsedQuery {
+    let mut query = base;
+    let mut errors = Vec::new();
+    let mut text_parts: Vec<&str> = Vec::new();
+
+    for (token, span) in tokenize(input, &mut errors) {
+        let Some((key, value)) = token.split_once(':') else {
+            text_parts.push(token);
+            continue;
+        };
+        let key = key.to_lowercase();
+        if !OPERATORS.contains(&key.as_str()) {
+            // e.g. "https://..." or "feat:" stays plain search text
+            text_parts.push(token);
+            continue;
+        }
+        let value = unquote(value);
+        if value.is_empty() {
+            errors.push(QueryError {
+                span,
+                message: format!("{key}: needs a value"),
+            });
+            continue;
+        }
+        if let Err(message) = apply_operator(&mut query, &key, value) {
+            errors.push(QueryError { span, message });
+        }
+    }
+
+    query.text = text_parts
+        .iter()
+        .map(|part| unquote(part))
+        .collect::<Vec<_>>()
+        .join(" ");
+    narrow_api_duration(&mut query);
+    // the search bar underlines them left to right; the quote error comes out of tokenize first
+    errors.sort_by_key(|error| error.span.start);
+    ParsedQuery { query, errors }
+}
+
+const OPERATORS: [&str; 9] = [
+    "dur", "after", "before", "channel", "order", "type", "region", "lang", "safe",
+];
+
+fn apply_operator(query: &mut SearchQuery, key: &str, value: &str) -> Result<(), String> {
+    let filters = &mut query.filters;
+    match key {
+        "dur" => apply_duration(query, value)?,
+        "after" | "before" => {
+            if to_rfc3339(value).is_none() {
+                return Err(format!("{key}: expected YYYY, YYYY-MM or YYYY-MM-DD"));
+            }
+            if key == "after" {
+                filters.published_after = value.to_string();
+            } else {
+                filters.published_before = value.to_string();
+            }
+        }
+        "channel" => query.client_filter.channel = Some(value.to_string()),
+        "order" => {
+            filters.order = match value.to_lowercase().as_str() {
+                "relevance" => SearchOrder::Relevance,
+                "date" | "new" | "newest" => SearchOrder::Date,
+                "views" | "viewcount" => SearchOrder::ViewCount,
+                "rating" => SearchOrder::Rating,
+                _ => return Err("order: use relevance, date, views or rating".to_string()),
+            }
+        }
+        "type" => {
+            query.search_type = match value.to_lowercase().as_str() {
+                "video" | "videos" => SearchType::Video,
+                "channel" | "channels" => SearchType::Channel,
+                "playlist" | "playlists" => SearchType::Playlist,
+                "all" => SearchType::All,
+                _ => return Err("type: use video, channel, playlist or all".to_string()),
+            }
+        }
+        "region" => {
+            if value.len() != 2 || !value.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err("region: expected a two letter country code".to_string());
+            }
+            filters.region_code = value.to_uppercase();
+        }
+        "lang" => filters.relevance_language = value.to_lowercase(),
+        "safe" => {
+            filters.safe_search = match value.to_lowercase().as_str() {
+                "off" | "none" => SafeSearch::None,
+                "moderate" => SafeSearch::Moderate,
+                "strict" => SafeSearch::Strict,
+                _ => return Err("safe: use off, moderate or strict".to_string()),
+            }
+        }
+        _ => unreachable!("operator list and match are out of sync"),
+    }
+    Ok(())
+}
+
+// dur:short|medium|long goes to the API, ranges (>10m, <=4m, 3m-5m) are checked client-side
+fn apply_duration(query: &mut SearchQuery, value: &str) -> Result<(), String> {
+    let filter = &mut query.client_filter;
+    let keyword = match value.to_lowercase().as_str() {
+        "short" => Some(VideoDurationFilter::Short),
+        "medium" => Some(VideoDurationFilter::Medium),
+        "long" => Some(VideoDurationFilter::Long),
+        _ => None,
+    };
+    if let Some(keyword) = keyword {
+        query.filters.video_duration = keyword;
+        return Ok(());
+    }
+
+    if let Some(rest) = value.strip_prefix(">=").or_else(|| value.strip_prefix('>')) {
+        filter.min_duration = Some(parse_duration_spec(rest)?);
+    } else if let Some(rest) = value.strip_prefix("<=").or_else(|| value.strip_prefix('<')) {
+        filter.max_duration = Some(parse_duration_spec(rest)?);
+    } else if let Some((min, max)) = value.split_once('-') {
+        let (min, max) = (parse_duration_spec(min)?, parse_duration_spec(max)?);
+        if min > max {
+            return Err("dur: range start is after its end".to_string());
+        }
+        filter.min_duration = Some(min);
+        filter.max_duration = Some(max);
+    } else {
+        return Err("dur: use short, medium, long, >10m, <4m or 3m-5m".to_string());
+    }
+    Ok(())
+}
+
+// Picks the API length bucket that contains the whole client-side range, so fewer
+// results get thrown away after the request
+fn narrow_api_duration(query: &mut SearchQuery) {
+    if query.filters.video_duration != VideoDurationFilter::Any {
+        return;
+    }
+    let filter = &query.client_filter;
+    let four_minutes = Duration::from_secs(4 * 60);
+    let twenty_minutes = Duration::from_secs(20 * 60);
+    if filter.min_duration.is_some_and(|min| min >= twenty_minutes) {
+        query.filters.video_duration = VideoDurationFilter::Long;
+    } else if filter.max_duration.is_some_and(|max| max < four_minutes) {
+        query.filters.video_duration = VideoDurationFilter::Short;
+    } else if filter.min_duration.is_some_and(|min| min >= four_minutes)
+        && filter.max_duration.is_some_and(|max| max <= twenty_minutes)
+    {
+        query.filters.video_duration = VideoDurationFilter::Medium;
+    }
+}
+
+// "90s", "10m", "1h30m", "1:30:00", "4:05" or a bare number of minutes
+pub fn parse_duration_spec(value: &str) -> Result<Duration, String> {
+    let unreadable = || format!("dur: cannot read \"{value}\"");
+    let value = value.trim().to_lowercase();
+    if value.is_empty() {
+        return Err(unreadable());
+    }
+    if value.contains(':') {
+        let mut seconds: u64 = 0;
+        for part in value.split(':') {
+            let part = part.parse::<u64>().map_err(|_| unreadable())?;
+            seconds = seconds
+                .checked_mul(60)
+                .and_then(|seconds| seconds.checked_add(part))
+                .ok_or_else(too_long)?;
+        }
+        return Ok(Duration::from_secs(seconds));
+    }
+    if let Ok(minutes) = value.parse::<f64>() {
+        return seconds_to_duration(minutes * 60.0);
+    }
+
+    let mut seconds = 0.0;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let amount: f64 = number.parse().map_err(|_| unreadable())?;
+        number.clear();
+        seconds += amount
+            * match c {
+                'h' => 3_600.0,
+                'm' => 60.0,
+                's' => 1.0,
+                _ => return Err(unreadable()),
+            };
+    }
+    if !number.is_empty() {
+        return Err(unreadable());
+    }
+    seconds_to_duration(seconds)
+}
+
+// parse::<f64>() also accepts "-5", "nan", "inf" and "1e300", which Duration cannot hold
+fn seconds_to_duration(seconds: f64) -> Result<Duration, String> {
+    if !seconds.is_finite() {
+        return Err("dur: not a number".to_string());
+    }
+    if seconds < 0.0 {
+        return Err("dur: durations cannot be negative".to_string());
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|_| too_long())
+}
+
+fn too_long() -> String {
+    "dur: duration is too long".to_string()
+}
+
+// Splits on whitespace outside of double quotes and keeps the byte span of every token
+fn tokenize<'a>(input: &'a str, errors: &mut Vec<QueryError>) -> Vec<(&'a str, Range<usize>)> {
+    let mut tokens = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut in_quotes = false;
+
+    for (index, c) in input.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        if c.is_whitespace() && !in_quotes {
+            if let Some(begin) = start.take() {
+                tokens.push((&input[begin..index], begin..index));
+            }
+        } else if start.is_none() {
+            start = Some(index);
+        }
+    }
+    if let Some(begin) = start {
+        tokens.push((&input[begin..], begin..input.len()));
+    }
+    if in_quotes {
+        let quote = input.rfind('"').unwrap_or(0);
+        errors.push(QueryError {
+            span: quote..input.len(),
+            message: "missing closing quote".to_string(),
+        });
+    }
+    tokens
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.strip_prefix('"').unwrap_or(value);
+    value.strip_suffix('"').unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> ParsedQuery {
+        parse_query(input, SearchQuery::default())
+    }
+
+    #[test]
+    fn splits_operators_from_text() {
+        let parsed = parse(r#"lofi dur:>10m after:2024-01 channel:"Chillhop" order:views"#);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let query = parsed.query;
+        assert_eq!(query.text, "lofi");
+        assert_eq!(
+            query.client_filter.min_duration,
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(query.client_filter.channel.as_deref(), Some("Chillhop"));
+        assert_eq!(query.filters.published_after, "2024-01");
+        assert_eq!(query.filters.order, SearchOrder::ViewCount);
+    }
+
+    #[test]
+    fn keeps_quoted_phrases_and_unknown_keys_as_text() {
+        let parsed = parse(r#""daft punk" live https://example.com"#);
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.query.text, "daft punk live https://example.com");
+    }
+
+    #[test]
+    fn quoted_operator_values_may_contain_spaces() {
+        let parsed = parse(r#"channel:"Lofi Girl" beats"#);
+        assert_eq!(
+            parsed.query.client_filter.channel.as_deref(),
+            Some("Lofi Girl")
+        );
+        assert_eq!(parsed.query.text, "beats");
+    }
+
+    #[test]
+    fn duration_ranges_narrow_the_api_bucket() {
+        let parsed = parse("mix dur:>30m");
+        assert_eq!(
+            parsed.query.filters.video_duration,
+            VideoDurationFilter::Long
+        );
+
+        let parsed = parse("song dur:2m-3m30s");
+        assert_eq!(
+            parsed.query.filters.video_duration,
+            VideoDurationFilter::Short
+        );
+        assert_eq!(
+            parsed.query.client_filter.max_duration,
+            Some(Duration::from_secs(210))
+        );
+
+        let parsed = parse("dur:medium");
+        assert_eq!(
+            parsed.query.filters.video_duration,
+            VideoDurationFilter::Medium
+        );
+        assert!(parsed.query.client_filter.is_empty());
+    }
+
+    #[test]
+    fn reports_errors_with_spans() {
+        let input = "lofi dur:>abc order:best";
+        let parsed = parse(input);
+        assert_eq!(parsed.errors.len(), 2);
+        assert_eq!(&input[parsed.errors[0].span.clone()], "dur:>abc");
+        assert_eq!(&input[parsed.errors[1].span.clone()], "order:best");
+        assert_eq!(parsed.query.text, "lofi");
+    }
+
+    #[test]
+    fn reports_unterminated_quotes_and_empty_values() {
+        let parsed = parse(r#"channel:"Chill"#);
+        assert!(parsed.errors.iter().any(|e| e.message.contains("quote")));
+
+        let parsed = parse("after:");
+        assert_eq!(parsed.errors.len(), 1);
+    }
+
+    #[test]
+    fn sorts_errors_by_position() {
+        let input = r#"dur:>abc "lofi"#;
+        let parsed = parse(input);
+        assert_eq!(parsed.errors.len(), 2);
+        assert_eq!(&input[parsed.errors[0].span.clone()], "dur:>abc");
+        assert_eq!(&input[parsed.errors[1].span.clone()], "\"lofi");
+    }
+
+    #[test]
+    fn parses_duration_specs() {
+        assert_eq!(parse_duration_spec("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration_spec("1h30m"), Ok(Duration::from_secs(5_400)));
+        assert_eq!(parse_duration_spec("4:05"), Ok(Duration::from_secs(245)));
+        assert_eq!(
+            parse_duration_spec("1:00:00"),
+            Ok(Duration::from_secs(3_600))
+        );
+        assert_eq!(parse_duration_spec("10"), Ok(Duration::from_secs(600)));
+        assert!(parse_duration_spec("10x").is_err());
+        assert!(parse_duration_spec("").is_err());
+    }
+
+    #[test]
+    fn rejects_durations_that_do_not_fit() {
+        for value in ["-5", "nan", "inf", "1e300", "99999999999999999999:00"] {
+            assert!(parse_duration_spec(value).is_err(), "{value}");
+        }
+        for input in ["dur:>-5", "dur:<nan", "dur:>inf", "dur:<1e300"] {
+            let parsed = parse(input);
+            assert_eq!(parsed.errors.len(), 1, "{input}");
+            assert_eq!(parsed.errors[0].span, 0..input.len());
+        }
+    }
+}
@@ -0,0 +1,569 @@
+use crate::error::AppError;
+use crate::playlist::PlaylistPosition;
+use crate::profiles::DownloadProfile;
+use crate::share::{DownloadPhase, DownloadProgress};
+use crate::split::TrackSplit;
+use crate::tags::TrackTags;
+use std::path::PathBuf;
+use tokio::sync::oneshot;
+
+pub type JobId = u64;
+pub type GroupId = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Queued,
+    Running,
+    PostProcessing,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Running => "downloading",
+            JobState::PostProcessing => "post-processing",
+            JobState::Done => "done",
+            JobState::Failed => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+
+    // yt-dlp is running for this job
+    pub fn is_active(&self) -> bool {
+        matches!(self, JobState::Running | JobState::PostProcessing)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobState::Done | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+pub struct DownloadJob {
+    pub id: JobId,
+    pub video_id: String,
+    pub title: String,
+    pub profile: DownloadProfile,
+    // download even if the video is already in the archive
+    pub force: bool,
+    pub state: JobState,
+    pub progress: Option<DownloadProgress>,
+    pub error: Option<AppError>,
+    // where the finished download ended up
+    pub files: Vec<PathBuf>,
+    // set for entries of a playlist download
+    pub playlist: Option<PlaylistPosition>,
+    pub group: Option<GroupId>,
+    // written after the download instead of the YouTube metadata
+    pub tags: Option<TrackTags>,
+    // one file per chapter instead of one for the whole video
+    pub split: Option<TrackSplit>,
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupState {
+    // yt-dlp is listing the entries
+    Enumerating,
+    Ready,
+    Failed(AppError),
+}
+
+// A playlist download; its entries are ordinary jobs that point back to the group
+pub struct PlaylistGroup {
+    pub id: GroupId,
+    pub playlist_id: String,
+    pub title: String,
+    pub profile: DownloadProfile,
+    // entry ranges like "1-5,8", empty for all
+    pub range: String,
+    pub skip_downloaded: bool,
+    pub state: GroupState,
+    // entries left out because they were downloaded before
+    pub skipped: usize,
+}
+
+// A job the UI has to spawn; the worker watches `cancel` and kills yt-dlp when it fires
+pub struct JobStart {
+    pub id: JobId,
+    pub video_id: String,
+    pub title: String,
+    pub profile: DownloadProfile,
+    pub force: bool,
+    pub playlist: Option<PlaylistPosition>,
+    pub tags: Option<TrackTags>,
+    pub split: Option<TrackSplit>,
+    pub cancel: oneshot::Receiver<()>,
+}
+
+// Central list of downloads. Jobs are started in click order, at most `max_concurrent` at once.
+// Pausing only holds back queued jobs, running downloads are finished.
+#[derive(Default)]
+pub struct DownloadQueue {
+    jobs: Vec<DownloadJob>,
+    groups: Vec<PlaylistGroup>,
+    next_id: JobId,
+    next_group_id: GroupId,
+    pub paused: bool,
+}
+
+impl DownloadQueue {
+    pub fn push(
+        &mut self,
+        video_id: String,
+        title: String,
+        profile: DownloadProfile,
+        force: bool,
+    ) -> JobId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.jobs.push(DownloadJob {
+            id,
+            video_id,
+            title,
+            profile,
+            force,
+            state: JobState::Queued,
+            progress: None,
+            error: None,
+            files: Vec::new(),
+            playlist: None,
+            group: None,
+            tags: None,
+            split: None,
+            cancel: None,
+        });
+        id
+    }
+
+    // Group waiting for its entry list; the entries are added with `fill_group`
+    pub fn push_group(
+        &mut self,
+        playlist_id: String,
+        title: String,
+        profile: DownloadProfile,
+        range: String,
+        skip_downloaded: bool,
+    ) -> GroupId {
+        let id = self.next_group_id;
+        self.next_group_id += 1;
+        self.groups.push(PlaylistGroup {
+            id,
+            playlist_id,
+            title,
+            profile,
+            range,
+            skip_downloaded,
+            state: GroupState::Enumerating,
+            skipped: 0,
+        });
+        id
+    }
+
+    pub fn groups(&self) -> &[PlaylistGroup] {
+        &self.groups
+    }
+
+    pub fn group(&self, id: GroupId) -> Option<&PlaylistGroup> {
+        self.groups.iter().find(|group| group.id == id)
+    }
+
+    // Queues one child job per entry, in playlist order
+    pub fn fill_group(
+        &mut self,
+        id: GroupId,
+        title: String,
+        entries: Vec<(String, String, PlaylistPosition)>,
+        skipped: usize,
+    ) {
+        let Some(group) = self.groups.iter_mut().find(|group| group.id == id) else {
+            return;
+        };
+        group.state = GroupState::Ready;
+        group.skipped = skipped;
+        if !title.is_empty() {
+            group.title = title;
+        }
+        let profile = group.profile.clone();
+        // without the archive check yt-dlp downloads entries that are already in the history
+        let force = !group.skip_downloaded;
+        for (video_id, title, position) in entries {
+            let job = self.push(video_id, title, profile.clone(), force);
+            if let Some(job) = self.job_mut(job) {
+                job.playlist = Some(position);
+                job.group = Some(id);
+            }
+        }
+    }
+
+    pub fn fail_group(&mut self, id: GroupId, error: AppError) {
+        if let Some(group) = self.groups.iter_mut().find(|group| group.id == id) {
+            group.state = GroupState::Failed(error);
+        }
+    }
+
+    pub fn group_jobs(&self, id: GroupId) -> impl Iterator<Item = &DownloadJob> {
+        self.jobs.iter().filter(move |job| job.group == Some(id))
+    }
+
+    // Finished entries and overall percent of a group
+    pub fn group_progress(&self, id: GroupId) -> (usize, usize, f32) {
+        let (mut finished, mut total, mut percent) = (0, 0, 0.0);
+        for job in self.group_jobs(id) {
+            total += 1;
+            if job.state.is_finished() {
+                finished += 1;
+                percent += 100.0;
+            } else if let Some(progress) = &job.progress {
+                percent += progress.percent;
+            }
+        }
+        let percent = if total == 0 {
+            0.0
+        } else {
+            percent / total as f32
+        };
+        (finished, total, percent)
+    }
+
+    pub fn cancel_group(&mut self, id: GroupId) {
+        let ids: Vec<JobId> = self.group_jobs(id).map(|job| job.id).collect();
+        for job in ids {
+            self.cancel(job);
+        }
+    }
+
+    pub fn jobs(&self) -> &[DownloadJob] {
+        &self.jobs
+    }
+
+    pub fn get(&self, id: JobId) -> Option<&DownloadJob> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    fn job_mut(&mut self, id: JobId) -> Option<&mut DownloadJob> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.jobs.iter().filter(|job| job.state.is_active()).count()
+    }
+
+    // Latest job for this video, so a result row can show it again after a new search
+    pub fn latest_job_for(&self, video_id: &str) -> Option<JobId> {
+        self.jobs
+            .iter()
+            .rev()
+            .find(|job| job.video_id == video_id)
+            .map(|job| job.id)
+    }
+
+    // Jobs that are queued or running
+    pub fn pending_count(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| !job.state.is_finished())
+            .count()
+    }
+
+    // Marks queued jobs as running until the limit is reached and hands them to the caller
+    pub fn start_next(&mut self, max_concurrent: usize) -> Vec<JobStart> {
+        let mut free = max_concurrent.max(1).saturating_sub(self.active_count());
+        let mut started = Vec::new();
+        if self.paused {
+            return started;
+        }
+        for job in self.jobs.iter_mut() {
+            if free == 0 {
+                break;
+            }
+            if job.state != JobState::Queued {
+                continue;
+            }
+            let (sender, receiver) = oneshot::channel();
+            job.state = JobState::Running;
+            job.cancel = Some(sender);
+            started.push(JobStart {
+                id: job.id,
+                video_id: job.video_id.clone(),
+                title: job.title.clone(),
+                profile: job.profile.clone(),
+                force: job.force,
+                playlist: job.playlist.clone(),
+                tags: job.tags.clone(),
+                split: job.split.clone(),
+                cancel: receiver,
+            });
+            free -= 1;
+        }
+        started
+    }
+
+    pub fn set_progress(&mut self, id: JobId, progress: DownloadProgress) {
+        let Some(job) = self.job_mut(id) else {
+            return;
+        };
+        if !job.state.is_active() {
+            return;
+        }
+        job.state = match progress.phase {
+            DownloadPhase::Downloading => JobState::Running,
+            DownloadPhase::PostProcessing(_) => JobState::PostProcessing,
+        };
+        job.progress = Some(progress);
+    }
+
+    // Final state reported by the worker
+    pub fn finish(&mut self, id: JobId, state: JobState, error: Option<AppError>) {
+        if let Some(job) = self.job_mut(id) {
+            job.state = state;
+            job.error = error;
+            job.cancel = None;
+            if state != JobState::Done {
+                job.progress = None;
+            }
+        }
+    }
+
+    pub fn set_tags(&mut self, id: JobId, tags: TrackTags) {
+        if let Some(job) = self.job_mut(id) {
+            job.tags = Some(tags);
+        }
+    }
+
+    pub fn set_split(&mut self, id: JobId, split: TrackSplit) {
+        if let Some(job) = self.job_mut(id) {
+            job.split = Some(split);
+        }
+    }
+
+    pub fn set_files(&mut self, id: JobId, files: Vec<PathBuf>) {
+        if let Some(job) = self.job_mut(id) {
+            job.files = files;
+        }
+    }
+
+    // Queued jobs are dropped right away, running ones once yt-dlp has been killed
+    pub fn cancel(&mut self, id: JobId) {
+        let Some(job) = self.job_mut(id) else {
+            return;
+        };
+        match job.state {
+            JobState::Queued => job.state = JobState::Cancelled,
+            JobState::Running | JobState::PostProcessing => {
+                if let Some(cancel) = job.cancel.take() {
+                    let _ = cancel.send(());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Puts a failed or cancelled job back at the end of the queue
+    pub fn retry(&mut self, id: JobId) -> bool {
+        let Some(position) = self.jobs.iter().position(|job| job.id == id) else {
+            return false;
+        };
+        if !matches!(
+            self.jobs[position].state,
+            JobState::Failed | JobState::Cancelled
+        ) {
+            return false;
+        }
+        let mut job = self.jobs.remove(position);
+        job.state = JobState::Queued;
+        job.error = None;
+        job.progress = None;
+        self.jobs.push(job);
+        true
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(|job| !job.state.is_finished());
+        // groups stay while they still have entries or are being listed
+        let jobs = &self.jobs;
+        self.groups.retain(|group| {
+            group.state == GroupState::Enumerating
+                || jobs.iter().any(|job| job.group == Some(group.id))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue_with(count: usize) -> DownloadQueue {
+        let mut queue = DownloadQueue::default();
+        for index in 0..count {
+            queue.push(
+                format!("video{index}"),
+                format!("title {index}"),
+                DownloadProfile::default(),
+                false,
+            );
+        }
+        queue
+    }
+
+    fn states(queue: &DownloadQueue) -> Vec<JobState> {
+        queue.jobs().iter().map(|job| job.state).collect()
+    }
+
+    #[test]
+    fn respects_the_concurrency_limit() {
+        let mut queue = queue_with(3);
+        let started = queue.start_next(2);
+        assert_eq!(started.len(), 2);
+        assert_eq!(started[0].video_id, "video0");
+        assert!(queue.start_next(2).is_empty());
+
+        queue.finish(started[0].id, JobState::Done, None);
+        let started = queue.start_next(2);
+        assert_eq!(started.len(), 1);
+        assert_eq!(started[0].video_id, "video2");
+    }
+
+    #[test]
+    fn paused_queue_starts_nothing() {
+        let mut queue = queue_with(2);
+        queue.paused = true;
+        assert!(queue.start_next(4).is_empty());
+        queue.paused = false;
+        assert_eq!(queue.start_next(4).len(), 2);
+    }
+
+    #[test]
+    fn cancel_signals_running_jobs() {
+        let mut queue = queue_with(2);
+        let mut started = queue.start_next(1);
+        let id = started[0].id;
+
+        // running job: only the signal, the worker then reports Cancelled
+        queue.cancel(id);
+        assert!(started[0].cancel.try_recv().is_ok());
+        assert_eq!(states(&queue), [JobState::Running, JobState::Queued]);
+
+        // a queued job is cancelled right away
+        queue.cancel(1);
+        assert_eq!(states(&queue), [JobState::Running, JobState::Cancelled]);
+    }
+
+    #[test]
+    fn retry_requeues_failed_jobs_at_the_end() {
+        let mut queue = queue_with(2);
+        let started = queue.start_next(1);
+        queue.finish(started[0].id, JobState::Failed, Some("boom".into()));
+        assert!(queue.retry(started[0].id));
+        assert_eq!(states(&queue), [JobState::Queued, JobState::Queued]);
+        assert_eq!(queue.jobs()[1].id, started[0].id);
+        assert_eq!(queue.jobs()[1].error, None);
+        // only failed or cancelled jobs
+        assert!(!queue.retry(1));
+    }
+
+    #[test]
+    fn finds_the_latest_job_of_a_video() {
+        let mut queue = queue_with(2);
+        let again = queue.push(
+            "video0".into(),
+            "title 0".into(),
+            DownloadProfile::default(),
+            false,
+        );
+        assert_eq!(queue.latest_job_for("video0"), Some(again));
+        assert_eq!(queue.latest_job_for("video1"), Some(1));
+        assert_eq!(queue.latest_job_for("unknown"), None);
+    }
+
+    #[test]
+    fn playlist_groups_track_their_entries() {
+        let mut queue = DownloadQueue::default();
+        let group = queue.push_group(
+            "PL1".into(),
+            "Playlist".into(),
+            DownloadProfile::default(),
+            String::new(),
+            true,
+        );
+        assert_eq!(queue.group(group).unwrap().state, GroupState::Enumerating);
+        let entries = (1..=3)
+            .map(|index| {
+                let position = PlaylistPosition {
+                    playlist_id: "PL1".into(),
+                    title: "Mix".into(),
+                    index,
+                    count: 3,
+                };
+                (format!("v{index}"), format!("Track {index}"), position)
+            })
+            .collect();
+        queue.fill_group(group, "Mix".into(), entries, 2);
+        assert_eq!(queue.group(group).unwrap().title, "Mix");
+        assert_eq!(queue.group(group).unwrap().skipped, 2);
+
+        let started = queue.start_next(1);
+        assert_eq!(started[0].playlist.as_ref().unwrap().index, 1);
+        queue.finish(started[0].id, JobState::Done, None);
+        assert_eq!(queue.group_progress(group), (1, 3, 100.0 / 3.0));
+
+        queue.cancel_group(group);
+        assert_eq!(queue.group_progress(group).0, 3);
+        queue.clear_finished();
+        assert!(queue.group(group).is_none());
+    }
+
+    #[test]
+    fn groups_without_skipping_force_their_entries() {
+        let mut queue = DownloadQueue::default();
+        let position = PlaylistPosition {
+            playlist_id: "PL1".into(),
+            title: "Mix".into(),
+            index: 1,
+            count: 1,
+        };
+        let skipping = queue.push_group(
+            "PL1".into(),
+            "Mix".into(),
+            DownloadProfile::default(),
+            String::new(),
+            true,
+        );
+        let everything = queue.push_group(
+            "PL1".into(),
+            "Mix".into(),
+            DownloadProfile::default(),
+            String::new(),
+            false,
+        );
+        let entry = ("v1".to_string(), "Track 1".to_string(), position);
+        queue.fill_group(skipping, String::new(), vec![entry.clone()], 0);
+        queue.fill_group(everything, String::new(), vec![entry], 0);
+
+        assert_eq!(queue.jobs()[1].group, Some(everything));
+        let started = queue.start_next(2);
+        assert!(!started[0].force);
+        assert!(started[1].force);
+    }
+
+    #[test]
+    fn progress_moves_jobs_into_post_processing() {
+        let mut queue = queue_with(1);
+        let id = queue.start_next(1)[0].id;
+        queue.set_progress(
+            id,
+            DownloadProgress {
+                phase: DownloadPhase::PostProcessing("ExtractAudio".into()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(states(&queue), [JobState::PostProcessing]);
+        assert_eq!(queue.active_count(), 1);
+    }
+}
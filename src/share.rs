@@ -1,11 +1,51 @@
+use crate::error::{AppError, AppResult};
+use crate::metadata::{Chapter, PlaylistMetadata, VideoMetadata};
+use crate::output_template::DEFAULT_OUTPUT_TEMPLATE;
+use crate::profiles::DownloadProfile;
+use crate::queue::{GroupId, JobId};
+use crate::split::TrackSplit;
+use crate::tags::TrackTags;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
 use tokio::sync::mpsc;
 
 pub enum WorkerMessage {
-    Data(SearchResponse),
-    Progress(u32),
-    Error(String),
-    Done(usize),
+    // results of a search or drill-down; `generation` tells replies of older searches apart
+    Data {
+        generation: u64,
+        data: SearchResponse,
+    },
+    NextPage {
+        generation: u64,
+        data: SearchResponse,
+    },
+    ApiKeyChecked(AppResult<()>),
+    // latest progress of a download job
+    Progress(JobId, DownloadProgress),
+    // failed search or download; download errors carry their job so the row can show them
+    Error {
+        job: Option<JobId>,
+        error: AppError,
+    },
+    // finished download and the files it produced
+    Done(JobId, Vec<PathBuf>),
+    DownloadCancelled(JobId),
+    // entry list of a playlist download
+    PlaylistEntries {
+        group: GroupId,
+        result: AppResult<PlaylistMetadata>,
+    },
+    // yt-dlp -J answer for the format inspector or the chapter split
+    Metadata {
+        video_id: String,
+        result: AppResult<Box<VideoMetadata>>,
+    },
+    // tag editor finished rewriting existing files
+    TagsWritten {
+        files: Vec<PathBuf>,
+        result: AppResult<()>,
+    },
 }
 
 // Tokio worker for async operations
@@ -21,6 +61,83 @@ impl Default for TokioWorker {
     }
 }
 
+// State of the key check on the warning screen
+#[derive(Default)]
+pub enum KeyCheck {
+    #[default]
+    Idle,
+    Checking,
+    Failed(String),
+}
+
+// Format table of one video; `metadata` stays None until yt-dlp -J has answered
+pub struct FormatInspector {
+    pub video_id: String,
+    pub title: String,
+    pub metadata: Option<AppResult<VideoMetadata>>,
+    // profile whose post-processing (conversion, container, tags) is used with the picked format
+    pub profile: String,
+}
+
+// Where the tracks of a split come from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TrackSource {
+    Chapters,
+    Description,
+}
+
+impl TrackSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TrackSource::Chapters => "video chapters",
+            TrackSource::Description => "description tracklist",
+        }
+    }
+}
+
+// Track list of one video, previewed and editable before the split download is queued
+pub struct ChapterSplitter {
+    pub video_id: String,
+    pub title: String,
+    // tag proposal from the search result, used once the chapters are known
+    pub guess: TrackTags,
+    pub metadata: Option<AppResult<VideoMetadata>>,
+    // both candidates as they were detected; `split` holds the edited copy of one of them
+    pub chapters: Vec<Chapter>,
+    pub tracklist: Vec<Chapter>,
+    pub source: TrackSource,
+    pub split: TrackSplit,
+    pub profile: String,
+}
+
+// Options asked before a playlist is downloaded
+pub struct PlaylistDialog {
+    pub playlist_id: String,
+    pub title: String,
+    // entry ranges like "1-5,8", empty for all
+    pub range: String,
+    pub skip_downloaded: bool,
+    pub profile: String,
+}
+
+// What the tag editor writes to: a download that has not started yet or files on disk
+pub enum TagTarget {
+    Download { video_id: String, title: String },
+    Files(Vec<PathBuf>),
+}
+
+// Artist/title/album editor, proposed from the YouTube title before the user edits it
+pub struct TagEditor {
+    pub target: TagTarget,
+    pub tags: TrackTags,
+    // download profile for TagTarget::Download
+    pub profile: String,
+    // media files of the download folder to pick from; empty unless opened from the queue
+    pub folder: Vec<PathBuf>,
+    // ffmpeg is rewriting the files
+    pub writing: bool,
+}
+
 // Panel size structure for UI layout
 pub struct PanelSize {
     pub side_width: f32,
@@ -34,17 +151,412 @@ pub enum AppState {
     App,
     Settings,
     Warning,
+    Queue,
+    Formats,
+    Chapters,
     Test,
 }
 
+// Result type filter for the search (type= parameter)
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+pub enum SearchType {
+    #[default]
+    Video,
+    Channel,
+    Playlist,
+    All,
+}
+
+impl SearchType {
+    pub const ALL: [SearchType; 4] = [
+        SearchType::Video,
+        SearchType::Channel,
+        SearchType::Playlist,
+        SearchType::All,
+    ];
+
+    pub fn api_value(&self) -> &'static str {
+        match self {
+            SearchType::Video => "video",
+            SearchType::Channel => "channel",
+            SearchType::Playlist => "playlist",
+            SearchType::All => "video,channel,playlist",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SearchType::Video => "Videos",
+            SearchType::Channel => "Channels",
+            SearchType::Playlist => "Playlists",
+            SearchType::All => "All",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+pub enum SearchOrder {
+    #[default]
+    Relevance,
+    Date,
+    ViewCount,
+    Rating,
+}
+
+impl SearchOrder {
+    pub const ALL: [SearchOrder; 4] = [
+        SearchOrder::Relevance,
+        SearchOrder::Date,
+        SearchOrder::ViewCount,
+        SearchOrder::Rating,
+    ];
+
+    pub fn api_value(&self) -> &'static str {
+        match self {
+            SearchOrder::Relevance => "relevance",
+            SearchOrder::Date => "date",
+            SearchOrder::ViewCount => "viewCount",
+            SearchOrder::Rating => "rating",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SearchOrder::Relevance => "Relevance",
+            SearchOrder::Date => "Upload date",
+            SearchOrder::ViewCount => "View count",
+            SearchOrder::Rating => "Rating",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+pub enum VideoDurationFilter {
+    #[default]
+    Any,
+    Short,
+    Medium,
+    Long,
+}
+
+impl VideoDurationFilter {
+    pub const ALL: [VideoDurationFilter; 4] = [
+        VideoDurationFilter::Any,
+        VideoDurationFilter::Short,
+        VideoDurationFilter::Medium,
+        VideoDurationFilter::Long,
+    ];
+
+    pub fn api_value(&self) -> &'static str {
+        match self {
+            VideoDurationFilter::Any => "any",
+            VideoDurationFilter::Short => "short",
+            VideoDurationFilter::Medium => "medium",
+            VideoDurationFilter::Long => "long",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            VideoDurationFilter::Any => "Any length",
+            VideoDurationFilter::Short => "Short (< 4 min)",
+            VideoDurationFilter::Medium => "Medium (4-20 min)",
+            VideoDurationFilter::Long => "Long (> 20 min)",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+pub enum SafeSearch {
+    #[default]
+    Moderate,
+    None,
+    Strict,
+}
+
+impl SafeSearch {
+    pub const ALL: [SafeSearch; 3] = [SafeSearch::Moderate, SafeSearch::None, SafeSearch::Strict];
+
+    pub fn api_value(&self) -> &'static str {
+        match self {
+            SafeSearch::Moderate => "moderate",
+            SafeSearch::None => "none",
+            SafeSearch::Strict => "strict",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SafeSearch::Moderate => "Moderate",
+            SafeSearch::None => "Off",
+            SafeSearch::Strict => "Strict",
+        }
+    }
+}
+
+// Filters of the search panel, persisted with the settings
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct SearchFilters {
+    pub order: SearchOrder,
+    // YYYY, YYYY-MM or YYYY-MM-DD
+    pub published_after: String,
+    pub published_before: String,
+    pub video_duration: VideoDurationFilter,
+    pub region_code: String,
+    pub relevance_language: String,
+    pub safe_search: SafeSearch,
+    pub music_only: bool,
+}
+
+// Everything call_yt_api needs for one search request
+#[derive(Clone, Debug, Default)]
+pub struct SearchQuery {
+    pub text: String,
+    pub max_results: i8,
+    pub search_type: SearchType,
+    pub filters: SearchFilters,
+    pub client_filter: ClientFilter,
+}
+
+// Filters the API cannot express; applied to each result page after the durations are known
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClientFilter {
+    pub min_duration: Option<Duration>,
+    pub max_duration: Option<Duration>,
+    pub channel: Option<String>,
+}
+
+impl ClientFilter {
+    pub fn is_empty(&self) -> bool {
+        self.min_duration.is_none() && self.max_duration.is_none() && self.channel.is_none()
+    }
+
+    pub fn matches(&self, item: &SearchItem) -> bool {
+        if let Some(channel) = &self.channel {
+            let channel_title = item.snippet.channel_title.to_lowercase();
+            if !channel_title.contains(&channel.to_lowercase()) {
+                return false;
+            }
+        }
+        // channels and playlists have no duration
+        if item.id.video_id.is_some()
+            && (self.min_duration.is_some() || self.max_duration.is_some())
+        {
+            let Some(duration) = item.video_durration else {
+                return false;
+            };
+            if self.min_duration.is_some_and(|min| duration < min) {
+                return false;
+            }
+            if self.max_duration.is_some_and(|max| duration > max) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// YouTube Music category of the Data API
+pub const MUSIC_CATEGORY_ID: &str = "10";
+
+impl SearchQuery {
+    // Query parameters of the search endpoint (without key and pageToken)
+    pub fn to_params(&self) -> Vec<(&'static str, String)> {
+        let filters = &self.filters;
+        let mut params = vec![
+            ("part", "snippet".to_string()),
+            ("q", self.text.clone()),
+            ("maxResults", self.max_results.to_string()),
+            ("type", self.search_type.api_value().to_string()),
+            ("order", filters.order.api_value().to_string()),
+            ("safeSearch", filters.safe_search.api_value().to_string()),
+        ];
+        if let Some(after) = to_rfc3339(&filters.published_after) {
+            params.push(("publishedAfter", after));
+        }
+        if let Some(before) = to_rfc3339(&filters.published_before) {
+            params.push(("publishedBefore", before));
+        }
+        if !filters.region_code.trim().is_empty() {
+            params.push(("regionCode", filters.region_code.trim().to_uppercase()));
+        }
+        if !filters.relevance_language.trim().is_empty() {
+            params.push((
+                "relevanceLanguage",
+                filters.relevance_language.trim().to_lowercase(),
+            ));
+        }
+        // videoDuration and videoCategoryId are only allowed with type=video
+        if self.search_type == SearchType::Video {
+            if filters.video_duration != VideoDurationFilter::Any {
+                params.push((
+                    "videoDuration",
+                    filters.video_duration.api_value().to_string(),
+                ));
+            }
+            if filters.music_only {
+                params.push(("videoCategoryId", MUSIC_CATEGORY_ID.to_string()));
+            }
+        }
+        params
+    }
+}
+
+// "2024", "2024-03" or "2024-03-15" -> "2024-03-15T00:00:00Z"
+pub fn to_rfc3339(date: &str) -> Option<String> {
+    let parts: Vec<&str> = date.trim().split('-').collect();
+    let valid =
+        |part: &str, len: usize| part.len() == len && part.chars().all(|c| c.is_ascii_digit());
+    let (year, month, day) = match parts.as_slice() {
+        [year] if valid(year, 4) => (*year, "01", "01"),
+        [year, month] if valid(year, 4) && valid(month, 2) => (*year, *month, "01"),
+        [year, month, day] if valid(year, 4) && valid(month, 2) && valid(day, 2) => {
+            (*year, *month, *day)
+        }
+        _ => return None,
+    };
+    let (year_number, month_number): (u32, u8) = (year.parse().ok()?, month.parse().ok()?);
+    let days = match month_number {
+        2 if year_number % 4 == 0 && (year_number % 100 != 0 || year_number % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    let day_ok = (1..=days).contains(&day.parse::<u8>().ok()?);
+    day_ok.then(|| format!("{year}-{month}-{day}T00:00:00Z"))
+}
+
+// Where search results come from
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+pub enum SearchBackendKind {
+    #[default]
+    DataApi,
+    YtDlp,
+    Invidious,
+    Piped,
+}
+
+impl SearchBackendKind {
+    pub const ALL: [SearchBackendKind; 4] = [
+        SearchBackendKind::DataApi,
+        SearchBackendKind::YtDlp,
+        SearchBackendKind::Invidious,
+        SearchBackendKind::Piped,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SearchBackendKind::DataApi => "YouTube Data API",
+            SearchBackendKind::YtDlp => "yt-dlp (no key)",
+            SearchBackendKind::Invidious => "Invidious instance",
+            SearchBackendKind::Piped => "Piped instance",
+        }
+    }
+
+    pub fn needs_instance(&self) -> bool {
+        matches!(
+            self,
+            SearchBackendKind::Invidious | SearchBackendKind::Piped
+        )
+    }
+}
+
+// A named search source to switch between, e.g. two Invidious instances
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct SearchProfile {
+    pub name: String,
+    pub kind: SearchBackendKind,
+    // Base URL of the Invidious/Piped instance, e.g. https://yewtu.be
+    pub instance_url: String,
+}
+
+impl SearchProfile {
+    // One per backend kind, instances still to be filled in
+    pub fn defaults() -> Vec<SearchProfile> {
+        SearchBackendKind::ALL
+            .into_iter()
+            .map(|kind| SearchProfile {
+                name: kind.label().to_string(),
+                kind,
+                instance_url: String::new(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+}
+
+// What the result list currently shows: the search or a drill-down into a channel/playlist
+#[derive(Clone, Default)]
+pub enum ListingSource {
+    #[default]
+    Search,
+    Channel {
+        channel_id: String,
+        title: String,
+    },
+    Playlist {
+        playlist_id: String,
+        title: String,
+    },
+}
+
 // Settings state
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct SettingsState {
     pub max_results: i8,
     pub first_run: bool,
     pub window_scaling: f32,
     pub download_path: String,
     pub personal_yt_api: String,
+    pub search_type: SearchType,
+    pub search_filters: SearchFilters,
+    pub search_profiles: Vec<SearchProfile>,
+    // name of the search profile results come from
+    pub search_profile: String,
+    // Data API base URL, empty for the default (or YT_API_BASE_URL)
+    pub api_base_url: String,
+    pub log_level: LogLevel,
+    pub max_concurrent_downloads: usize,
+    pub download_profiles: Vec<DownloadProfile>,
+    // name of the profile a plain click downloads with
+    pub default_profile: String,
+    // yt-dlp -o template relative to download_path
+    pub output_template: String,
+    // results that are already in the download history are left out
+    pub hide_downloaded: bool,
 }
 
 impl SettingsState {
@@ -55,8 +567,52 @@ impl SettingsState {
             window_scaling: 2.5,
             download_path: "".to_string(),
             personal_yt_api: "".to_string(),
+            search_type: SearchType::default(),
+            search_filters: SearchFilters::default(),
+            search_profiles: SearchProfile::defaults(),
+            search_profile: SearchBackendKind::default().label().to_string(),
+            api_base_url: "".to_string(),
+            log_level: LogLevel::default(),
+            max_concurrent_downloads: 2,
+            download_profiles: DownloadProfile::defaults(),
+            default_profile: "M4A".to_string(),
+            output_template: DEFAULT_OUTPUT_TEMPLATE.to_string(),
+            hide_downloaded: false,
         }
     }
+
+    // Default profile, or the first one if it was deleted or renamed
+    pub fn default_download_profile(&self) -> DownloadProfile {
+        self.download_profiles
+            .iter()
+            .find(|profile| profile.name == self.default_profile)
+            .or(self.download_profiles.first())
+            .cloned()
+            .unwrap_or_else(|| DownloadProfile::defaults().remove(0))
+    }
+
+    // Profile picked by name in a dialog; the default one if it was deleted or renamed since
+    pub fn profile_named(&self, name: &str) -> DownloadProfile {
+        self.download_profiles
+            .iter()
+            .find(|profile| profile.name == name)
+            .cloned()
+            .unwrap_or_else(|| self.default_download_profile())
+    }
+
+    // Selected search profile, or the first one if it was deleted or renamed
+    pub fn selected_search_profile(&self) -> SearchProfile {
+        self.search_profiles
+            .iter()
+            .find(|profile| profile.name == self.search_profile)
+            .or(self.search_profiles.first())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn selected_backend(&self) -> SearchBackendKind {
+        self.selected_search_profile().kind
+    }
 }
 
 impl Default for SettingsState {
@@ -68,6 +624,7 @@ impl Default for SettingsState {
 // UI Constants
 pub const WIDTH: f32 = 120.0;
 pub const HEIGHT: f32 = 120.0;
+pub const MAX_TOASTS: usize = 5;
 
 // Platform-specific paths
 #[cfg(target_os = "macos")]
@@ -82,9 +639,35 @@ pub const YT_DLP_BINARY: &str = "./yt_dlp/yt-dlp.exe";
 #[cfg(not(target_os = "windows"))]
 pub const YT_DLP_BINARY: &str = "./yt_dlp/yt-dlp_macos";
 
+pub const FFMPEG_BINARY: &str = "./ffmpeg/ffmpeg";
+
+// View model of one result row, rebuilt whenever new results arrive
 pub struct SearchResponseMeta {
-    pub is_enabled: bool,
-    pub download_progress: usize,
+    // video, playlist or channel id of the result
+    pub result_id: String,
+    // latest download of this video; the row is disabled while it is pending
+    pub job: Option<JobId>,
+}
+
+// Parsed yt-dlp progress of one download
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DownloadProgress {
+    pub phase: DownloadPhase,
+    // 0..=100
+    pub percent: f32,
+    pub downloaded_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+    // bytes per second
+    pub speed: Option<f64>,
+    pub eta: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum DownloadPhase {
+    #[default]
+    Downloading,
+    // name of the yt-dlp postprocessor, e.g. ExtractAudio
+    PostProcessing(String),
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -110,9 +693,7 @@ pub struct SearchItem {
     pub id: Id,
     pub snippet: Snippet,
     #[serde(skip)]
-    pub is_enabled: bool,
-    #[serde(skip)]
-    pub video_durration: Option<String>,
+    pub video_durration: Option<Duration>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -133,27 +714,201 @@ pub struct Id {
     pub playlist_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Id {
+    pub fn video(video_id: String) -> Self {
+        Self {
+            kind: "youtube#video".to_string(),
+            video_id: Some(video_id),
+            channel_id: None,
+            playlist_id: None,
+        }
+    }
+
+    pub fn channel(channel_id: String) -> Self {
+        Self {
+            kind: "youtube#channel".to_string(),
+            video_id: None,
+            channel_id: Some(channel_id),
+            playlist_id: None,
+        }
+    }
+
+    pub fn playlist(playlist_id: String) -> Self {
+        Self {
+            kind: "youtube#playlist".to_string(),
+            video_id: None,
+            channel_id: None,
+            playlist_id: Some(playlist_id),
+        }
+    }
+
+    // Id of whatever the result points to
+    pub fn result_id(&self) -> &str {
+        self.video_id
+            .as_deref()
+            .or(self.playlist_id.as_deref())
+            .or(self.channel_id.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+impl SearchItem {
+    // Result that did not come from the Data API (yt-dlp, Invidious, Piped)
+    pub fn new(id: Id, snippet: Snippet, video_durration: Option<Duration>) -> Self {
+        Self {
+            kind: "youtube#searchResult".to_string(),
+            etag: String::new(),
+            id,
+            snippet,
+            video_durration,
+        }
+    }
+
+    // Video entry built from a playlistItems result, so it renders like a search hit
+    pub fn from_playlist_item(item: PlaylistItem) -> Option<Self> {
+        let mut snippet = item.snippet;
+        let video_id = snippet.resource_id.as_ref()?.video_id.clone()?;
+        if let Some(owner) = snippet.video_owner_channel_title.take() {
+            snippet.channel_title = owner;
+        }
+        Some(Self {
+            kind: item.kind,
+            etag: item.etag,
+            id: Id::video(video_id),
+            snippet,
+            video_durration: None,
+        })
+    }
+}
+
+// One line of `yt-dlp --flat-playlist --dump-json`
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct YtDlpEntry {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub duration: Option<f64>,
+    pub channel: Option<String>,
+    pub channel_id: Option<String>,
+    pub uploader: Option<String>,
+    pub live_status: Option<String>,
+    pub thumbnails: Vec<ThumbnailData>,
+}
+
+impl SearchItem {
+    pub fn from_yt_dlp_entry(entry: YtDlpEntry) -> Option<Self> {
+        let video_id = entry.id?;
+        // smallest thumbnail, else the default image from i.ytimg.com
+        let thumbnail = entry
+            .thumbnails
+            .into_iter()
+            .min_by_key(|thumb| thumb.width.unwrap_or(u32::MAX))
+            .unwrap_or_else(|| {
+                ThumbnailData::url(format!("https://i.ytimg.com/vi/{video_id}/default.jpg"))
+            });
+        let live_broadcast_content = match entry.live_status.as_deref() {
+            Some("is_live") => "live",
+            Some("is_upcoming") => "upcoming",
+            _ => "none",
+        };
+        let snippet = Snippet {
+            channel_id: entry.channel_id.unwrap_or_default(),
+            title: entry.title.unwrap_or_default(),
+            description: entry.description.unwrap_or_default(),
+            thumbnails: Thumbnails::single(thumbnail),
+            channel_title: entry.channel.or(entry.uploader).unwrap_or_default(),
+            live_broadcast_content: live_broadcast_content.to_string(),
+            ..Default::default()
+        };
+        Some(Self::new(
+            Id::video(video_id),
+            snippet,
+            entry.duration.map(Duration::from_secs_f64),
+        ))
+    }
+}
+
+// Snippets of channel, playlist and playlistItems results omit some of the video fields
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Snippet {
-    #[serde(rename = "publishedAt")]
+    #[serde(rename = "publishedAt", default)]
     pub published_at: String,
-    #[serde(rename = "channelId")]
+    #[serde(rename = "channelId", default)]
     pub channel_id: String,
+    #[serde(default)]
     pub title: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(default)]
     pub thumbnails: Thumbnails,
-    #[serde(rename = "channelTitle")]
+    #[serde(rename = "channelTitle", default)]
     pub channel_title: String,
-    #[serde(rename = "liveBroadcastContent")]
+    #[serde(rename = "liveBroadcastContent", default)]
     pub live_broadcast_content: String,
+    #[serde(rename = "videoOwnerChannelTitle", default)]
+    pub video_owner_channel_title: Option<String>,
+    #[serde(rename = "resourceId", default)]
+    pub resource_id: Option<ResourceId>,
 }
+
 #[derive(Debug, Serialize, Deserialize)]
+pub struct ResourceId {
+    pub kind: String,
+    #[serde(rename = "videoId")]
+    pub video_id: Option<String>,
+}
+
+// playlistItems returns the item id as a plain string instead of an Id object
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct PlaylistItemsResponse {
+    #[serde(rename = "nextPageToken", default)]
+    pub next_page_token: String,
+    #[serde(rename = "pageInfo", default)]
+    pub page_info: Option<PageInfo>,
+    #[serde(default)]
+    pub items: Vec<PlaylistItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlaylistItem {
+    pub kind: String,
+    pub etag: String,
+    pub snippet: Snippet,
+}
+
+impl From<PlaylistItemsResponse> for SearchResponse {
+    fn from(response: PlaylistItemsResponse) -> Self {
+        Self {
+            next_page_token: response.next_page_token,
+            page_info: response.page_info,
+            items: response
+                .items
+                .into_iter()
+                .filter_map(SearchItem::from_playlist_item)
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Thumbnails {
     pub default: Option<ThumbnailData>,
     pub medium: Option<ThumbnailData>,
     pub high: Option<ThumbnailData>,
 }
 
+impl Thumbnails {
+    pub fn single(thumbnail: ThumbnailData) -> Self {
+        Self {
+            default: Some(thumbnail),
+            medium: None,
+            high: None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ThumbnailData {
     pub url: String,
@@ -161,6 +916,16 @@ pub struct ThumbnailData {
     pub height: Option<u32>,
 }
 
+impl ThumbnailData {
+    pub fn url(url: String) -> Self {
+        Self {
+            url,
+            width: None,
+            height: None,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct SearchDuration {
     items: Vec<SearchDurationItem>,
@@ -171,3 +936,85 @@ pub struct SearchDurationItem {
     video_id: String,
     video_durration: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_become_rfc3339() {
+        assert_eq!(to_rfc3339("2024").as_deref(), Some("2024-01-01T00:00:00Z"));
+        assert_eq!(
+            to_rfc3339(" 2024-03 ").as_deref(),
+            Some("2024-03-01T00:00:00Z")
+        );
+        assert_eq!(
+            to_rfc3339("2024-02-29").as_deref(),
+            Some("2024-02-29T00:00:00Z")
+        );
+        assert_eq!(
+            to_rfc3339("2000-02-29").as_deref(),
+            Some("2000-02-29T00:00:00Z")
+        );
+        assert_eq!(to_rfc3339("2023-02-29"), None);
+        assert_eq!(to_rfc3339("1900-02-29"), None);
+        assert_eq!(to_rfc3339("2024-02-31"), None);
+        assert_eq!(to_rfc3339("2024-04-31"), None);
+        assert_eq!(to_rfc3339("2024-13"), None);
+        assert_eq!(to_rfc3339("2024-00-10"), None);
+        assert_eq!(to_rfc3339("2024-1-5"), None);
+        assert_eq!(to_rfc3339(""), None);
+    }
+
+    #[test]
+    fn query_becomes_api_parameters() {
+        let query = SearchQuery {
+            text: "lofi".to_string(),
+            max_results: 25,
+            search_type: SearchType::Video,
+            filters: SearchFilters {
+                order: SearchOrder::ViewCount,
+                published_after: "2024-02".to_string(),
+                published_before: "2024-02-30".to_string(),
+                video_duration: VideoDurationFilter::Long,
+                region_code: " de ".to_string(),
+                music_only: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let params = query.to_params();
+        let value = |key: &str| {
+            params
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(value("q"), Some("lofi"));
+        assert_eq!(value("maxResults"), Some("25"));
+        assert_eq!(value("order"), Some("viewCount"));
+        assert_eq!(value("publishedAfter"), Some("2024-02-01T00:00:00Z"));
+        // the impossible date is left out instead of sent to the API
+        assert_eq!(value("publishedBefore"), None);
+        assert_eq!(value("regionCode"), Some("DE"));
+        assert_eq!(value("videoDuration"), Some("long"));
+        assert_eq!(value("videoCategoryId"), Some(MUSIC_CATEGORY_ID));
+
+        // duration and category only go with type=video
+        let all = SearchQuery {
+            search_type: SearchType::All,
+            ..query
+        };
+        let params = all.to_params();
+        assert!(
+            params
+                .iter()
+                .any(|(key, value)| *key == "type" && value == "video,channel,playlist")
+        );
+        assert!(
+            !params
+                .iter()
+                .any(|(key, _)| *key == "videoDuration" || *key == "videoCategoryId")
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use crate::metadata::Chapter;
+use crate::output_template::sanitize_filename;
+use crate::tags::TrackTags;
+use std::ffi::OsString;
+use std::path::Path;
+
+// Containers ffmpeg can write chapters into; FLAC, WAV and raw AAC have none
+pub const CHAPTER_MARKER_EXTENSIONS: [&str; 8] =
+    ["m4a", "mp4", "mka", "mkv", "webm", "opus", "ogg", "mp3"];
+
+// What the tracks of a download turn into
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TrackOutput {
+    // one tagged file per track
+    #[default]
+    Files,
+    // the whole mix as one file, with a .cue sheet and/or chapter markers
+    SingleFile,
+}
+
+impl TrackOutput {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TrackOutput::Files => "one file per track",
+            TrackOutput::SingleFile => "one file",
+        }
+    }
+}
+
+// Tracks of one download. Split into files they go into a folder named after the album,
+// "03 - Chapter title.mp3" each, tagged with track number, title, album and artist.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackSplit {
+    pub chapters: Vec<Chapter>,
+    pub artist: String,
+    // also the folder name; defaults to the video title
+    pub album: String,
+    pub year: String,
+    pub output: TrackOutput,
+    // only for TrackOutput::SingleFile
+    pub cue_sheet: bool,
+    pub chapter_markers: bool,
+}
+
+impl TrackSplit {
+    pub fn tags(&self, index: usize) -> TrackTags {
+        TrackTags {
+            artist: self.artist.clone(),
+            title: self.chapters[index].title.clone(),
+            album: self.album.clone(),
+            year: self.year.clone(),
+            track: format!("{}/{}", index + 1, self.chapters.len()),
+        }
+    }
+
+    // Path of a track relative to the download folder
+    pub fn track_path(&self, index: usize, extension: &str) -> String {
+        let width = self.chapters.len().to_string().len().max(2);
+        let title = sanitize_filename(&self.chapters[index].title);
+        format!(
+            "{}/{:0width$} - {title}.{extension}",
+            sanitize_filename(&self.album),
+            index + 1
+        )
+    }
+
+    // ffmpeg arguments that copy one chapter of `source` into `target`
+    pub fn ffmpeg_args(&self, index: usize, source: &Path, target: &Path) -> Vec<OsString> {
+        let chapter = &self.chapters[index];
+        let mut args: Vec<OsString> = vec![
+            "-v".into(),
+            "error".into(),
+            "-y".into(),
+            "-i".into(),
+            source.as_os_str().to_owned(),
+            "-ss".into(),
+            format!("{:.3}", chapter.start_time).into(),
+            "-to".into(),
+            format!("{:.3}", chapter.end_time).into(),
+            // copy audio and cover, but not the chapters of the whole video
+            "-map".into(),
+            "0".into(),
+            "-map_chapters".into(),
+            "-1".into(),
+            "-c".into(),
+            "copy".into(),
+        ];
+        args.extend(self.tags(index).ffmpeg_args().into_iter().map(Into::into));
+        args.push(target.as_os_str().to_owned());
+        args
+    }
+
+    // Tags of the single file: the album is also its title
+    pub fn album_tags(&self) -> TrackTags {
+        TrackTags {
+            artist: self.artist.clone(),
+            title: self.album.clone(),
+            album: self.album.clone(),
+            year: self.year.clone(),
+            track: String::new(),
+        }
+    }
+
+    // CUE sheet for `file_name`, which lies next to the .cue
+    pub fn cue_sheet(&self, file_name: &str) -> String {
+        let file_type = match file_name
+            .rsplit('.')
+            .next()
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            Some("mp3") => "MP3",
+            Some("aiff" | "aif") => "AIFF",
+            // most programs do the same for m4a, flac and opus
+            _ => "WAVE",
+        };
+        let mut cue = String::new();
+        if !self.year.trim().is_empty() {
+            cue.push_str(&format!("REM DATE {}\n", self.year.trim()));
+        }
+        if !self.artist.trim().is_empty() {
+            cue.push_str(&format!("PERFORMER {}\n", cue_quote(&self.artist)));
+        }
+        cue.push_str(&format!("TITLE {}\n", cue_quote(&self.album)));
+        cue.push_str(&format!("FILE {} {file_type}\n", cue_quote(file_name)));
+        for (index, chapter) in self.chapters.iter().enumerate() {
+            cue.push_str(&format!("  TRACK {:02} AUDIO\n", index + 1));
+            cue.push_str(&format!("    TITLE {}\n", cue_quote(&chapter.title)));
+            if !self.artist.trim().is_empty() {
+                cue.push_str(&format!("    PERFORMER {}\n", cue_quote(&self.artist)));
+            }
+            cue.push_str(&format!("    INDEX 01 {}\n", cue_time(chapter.start_time)));
+        }
+        cue
+    }
+
+    // ffmpeg metadata file with one [CHAPTER] per track, read with -map_chapters
+    pub fn ffmetadata(&self) -> String {
+        let mut metadata = String::from(";FFMETADATA1\n");
+        for chapter in &self.chapters {
+            metadata.push_str(&format!(
+                "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+                (chapter.start_time * 1000.0).round() as u64,
+                (chapter.end_time * 1000.0).round() as u64,
+                ffmetadata_escape(&chapter.title)
+            ));
+        }
+        metadata
+    }
+}
+
+pub fn supports_chapter_markers(extension: &str) -> bool {
+    CHAPTER_MARKER_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+}
+
+// CUE has no escaping; double quotes become single ones
+fn cue_quote(value: &str) -> String {
+    format!("\"{}\"", value.trim().replace('"', "'"))
+}
+
+// mm:ss:ff with 75 frames per second; minutes go past 99 for long mixes
+fn cue_time(seconds: f64) -> String {
+    let frames = (seconds * 75.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        frames / (75 * 60),
+        frames / 75 % 60,
+        frames % 75
+    )
+}
+
+fn ffmetadata_escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Chapters as yt-dlp reports them can have gaps, zero length or a missing end;
+// every track ends where the next one starts and the last one at the video's end, also
+// after the last track was removed in the preview
+pub fn clean_chapters(chapters: &[Chapter], duration: Option<f64>) -> Vec<Chapter> {
+    let mut sorted: Vec<Chapter> = chapters.to_vec();
+    sorted.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    let starts: Vec<f64> = sorted.iter().map(|chapter| chapter.start_time).collect();
+    sorted
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut chapter)| {
+            let next = starts.get(index + 1).copied();
+            chapter.end_time = match (next, duration) {
+                (Some(next), _) => next,
+                (None, Some(duration)) => duration,
+                (None, _) => chapter.end_time,
+            };
+            if chapter.title.trim().is_empty() {
+                chapter.title = format!("Track {}", index + 1);
+            }
+            chapter
+        })
+        .filter(|chapter| chapter.end_time > chapter.start_time)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(start_time: f64, end_time: f64, title: &str) -> Chapter {
+        Chapter {
+            start_time,
+            end_time,
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn closes_gaps_between_chapters() {
+        let chapters = [
+            chapter(200.0, 0.0, "Last"),
+            chapter(0.0, 90.0, "Intro"),
+            chapter(100.0, 100.0, ""),
+        ];
+        let cleaned = clean_chapters(&chapters, Some(300.0));
+        assert_eq!(
+            cleaned,
+            [
+                chapter(0.0, 100.0, "Intro"),
+                chapter(100.0, 200.0, "Track 2"),
+                chapter(200.0, 300.0, "Last"),
+            ]
+        );
+    }
+
+    #[test]
+    fn last_track_runs_to_the_end() {
+        let mut chapters = clean_chapters(
+            &[chapter(0.0, 0.0, "A"), chapter(100.0, 0.0, "B")],
+            Some(300.0),
+        );
+        chapters.pop();
+        assert_eq!(
+            clean_chapters(&chapters, Some(300.0)),
+            [chapter(0.0, 300.0, "A")]
+        );
+        // without a known duration the end stays
+        assert_eq!(clean_chapters(&chapters, None), [chapter(0.0, 100.0, "A")]);
+    }
+
+    #[test]
+    fn names_and_tags_each_track() {
+        let split = TrackSplit {
+            chapters: vec![chapter(0.0, 60.0, "Intro"), chapter(60.0, 200.0, "What?")],
+            artist: "Band".to_string(),
+            album: "Live: 2019".to_string(),
+            year: "2019".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(split.track_path(1, "mp3"), "Live_ 2019/02 - What_.mp3");
+        let tags = split.tags(1);
+        assert_eq!(tags.title, "What?");
+        assert_eq!(tags.track, "2/2");
+        assert_eq!(tags.album, "Live: 2019");
+
+        let args = split.ffmpeg_args(0, Path::new("in.mp3"), Path::new("out.mp3"));
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        assert!(args.windows(2).any(|pair| pair == ["-to", "60.000"]));
+        assert!(args.contains(&"track=1/2".to_string()));
+        assert_eq!(args.last().unwrap(), "out.mp3");
+    }
+
+    #[test]
+    fn writes_cue_sheets() {
+        let split = TrackSplit {
+            chapters: vec![
+                chapter(0.0, 61.5, "Intro"),
+                chapter(61.5, 6000.2, "Say \"Hi\""),
+            ],
+            artist: "Band".to_string(),
+            album: "Mix".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            split.cue_sheet("Mix.mp3"),
+            "PERFORMER \"Band\"\n\
+             TITLE \"Mix\"\n\
+             FILE \"Mix.mp3\" MP3\n  \
+             TRACK 01 AUDIO\n    \
+             TITLE \"Intro\"\n    \
+             PERFORMER \"Band\"\n    \
+             INDEX 01 00:00:00\n  \
+             TRACK 02 AUDIO\n    \
+             TITLE \"Say 'Hi'\"\n    \
+             PERFORMER \"Band\"\n    \
+             INDEX 01 01:01:38\n"
+        );
+        assert_eq!(cue_time(6000.2), "100:00:15");
+    }
+
+    #[test]
+    fn writes_ffmpeg_chapter_metadata() {
+        let split = TrackSplit {
+            chapters: vec![chapter(0.0, 1.5, "A=B; #1")],
+            ..Default::default()
+        };
+        assert_eq!(
+            split.ffmetadata(),
+            ";FFMETADATA1\n\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1500\ntitle=A\\=B\\; \\#1\n"
+        );
+        assert!(supports_chapter_markers("M4A"));
+        assert!(!supports_chapter_markers("flac"));
+    }
+}
@@ -0,0 +1,274 @@
+use crate::output_template::split_artist_title;
+use std::path::{Path, PathBuf};
+
+// Extensions the tag editor offers; thumbnails and .part files in the folder are ignored
+pub const MEDIA_EXTENSIONS: [&str; 10] = [
+    "mp3", "m4a", "aac", "opus", "ogg", "flac", "wav", "mp4", "mkv", "webm",
+];
+
+// Words that mark a bracket as YouTube decoration rather than part of the song title.
+// "(Live)", "(Remix)" or "(feat. X)" stay.
+const NOISE_WORDS: [&str; 14] = [
+    "official",
+    "video",
+    "audio",
+    "lyric",
+    "lyrics",
+    "visualizer",
+    "visualiser",
+    "hd",
+    "hq",
+    "4k",
+    "mv",
+    "m/v",
+    "clip",
+    "explicit",
+];
+
+// Tags for one song. Empty fields are not written, so the file keeps what it had.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackTags {
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    pub year: String,
+    // "3" or "3/12"
+    pub track: String,
+}
+
+impl TrackTags {
+    // Proposal from a YouTube title like "Artist - Song (Official Video) [HD]".
+    // Without a separator the channel is taken as the artist.
+    pub fn guess(title: &str, channel: &str, published_at: &str) -> Self {
+        let title = strip_noise(title);
+        let (artist, title) = match split_artist_title(&title) {
+            Some((artist, song)) => (artist, strip_noise(&song)),
+            None => (clean_channel(channel), title),
+        };
+        // publishedAt is RFC 3339, the first four characters are the year
+        let year = published_at
+            .get(..4)
+            .filter(|year| year.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or_default()
+            .to_string();
+        Self {
+            artist,
+            title,
+            year,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        [
+            &self.artist,
+            &self.title,
+            &self.album,
+            &self.year,
+            &self.track,
+        ]
+        .iter()
+        .all(|value| value.trim().is_empty())
+    }
+
+    // Problems that would end up as garbage in the file
+    pub fn validate(&self) -> Result<(), String> {
+        let year = self.year.trim();
+        let year_ok =
+            year.is_empty() || year.len() == 4 && year.chars().all(|c| c.is_ascii_digit());
+        if !year_ok {
+            return Err("the year needs four digits, e.g. 2019".to_string());
+        }
+        let track = self.track.trim();
+        let is_number = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        let track_ok = match track.split_once('/') {
+            Some((number, total)) => is_number(number) && is_number(total),
+            None => track.is_empty() || is_number(track),
+        };
+        if !track_ok {
+            return Err("the track is a number like 3 or 3/12".to_string());
+        }
+        Ok(())
+    }
+
+    // -metadata arguments for ffmpeg; ffmpeg maps the generic keys onto ID3, MP4 and Vorbis tags
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        [
+            ("artist", &self.artist),
+            ("title", &self.title),
+            ("album", &self.album),
+            ("date", &self.year),
+            ("track", &self.track),
+        ]
+        .into_iter()
+        .filter(|(_, value)| !value.trim().is_empty())
+        .flat_map(|(key, value)| ["-metadata".to_string(), format!("{key}={}", value.trim())])
+        .collect()
+    }
+}
+
+// Removes "(Official Video)", "[HD]", "| Official Audio" and the like
+pub fn strip_noise(title: &str) -> String {
+    let mut clean = String::new();
+    let mut rest = title;
+    while let Some(open) = rest.find(['(', '[']) {
+        let close_char = if rest[open..].starts_with('(') {
+            ')'
+        } else {
+            ']'
+        };
+        let Some(close) = rest[open..].find(close_char).map(|close| open + close) else {
+            break;
+        };
+        clean.push_str(&rest[..open]);
+        let inner = &rest[open + 1..close];
+        if !is_noise(inner) {
+            clean.push_str(&rest[open..=close]);
+        }
+        rest = &rest[close + 1..];
+    }
+    clean.push_str(rest);
+    // "Song | Official Video"
+    if let Some((song, suffix)) = clean.rsplit_once(" | ")
+        && is_noise(suffix)
+    {
+        clean = song.to_string();
+    }
+    clean.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_noise(text: &str) -> bool {
+    text.to_lowercase()
+        .split(|c: char| c.is_whitespace() || c == '-' || c == ',')
+        .any(|word| NOISE_WORDS.contains(&word))
+}
+
+// "Artist - Topic" and "ArtistVEVO" are auto-generated channel names
+pub fn clean_channel(channel: &str) -> String {
+    let channel = channel.trim();
+    let channel = channel.strip_suffix(" - Topic").unwrap_or(channel);
+    let channel = channel.strip_suffix("VEVO").unwrap_or(channel);
+    channel.trim().to_string()
+}
+
+// Raw ADTS .aac has no place for tags; ffmpeg writes the file and silently drops them
+pub fn holds_tags(extension: &str) -> bool {
+    !extension.eq_ignore_ascii_case("aac")
+}
+
+pub fn is_media_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| MEDIA_EXTENSIONS.contains(&ext.as_str()))
+}
+
+// Audio and video files directly in `dir`, sorted by name; the partial folder is skipped
+pub fn media_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file() && is_media_file(path))
+        .collect();
+    files.sort();
+    files
+}
+
+// Same for a folder yt-dlp wrote into, including subfolders from the output template
+pub fn media_files_below(dir: &Path) -> Vec<PathBuf> {
+    let mut files = media_files(dir);
+    let subdirs = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_dir());
+    for subdir in subdirs {
+        files.extend(media_files_below(&subdir));
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_youtube_decoration() {
+        assert_eq!(
+            strip_noise("Artist - Song (Official Video) [HD]"),
+            "Artist - Song"
+        );
+        assert_eq!(
+            strip_noise("Song (feat. Someone) [Official Lyric Video]"),
+            "Song (feat. Someone)"
+        );
+        assert_eq!(strip_noise("Song (Live) | Official Audio"), "Song (Live)");
+        assert_eq!(strip_noise("Song (Remix"), "Song (Remix");
+        assert_eq!(
+            strip_noise("Video Killed the Radio Star"),
+            "Video Killed the Radio Star"
+        );
+    }
+
+    #[test]
+    fn guesses_tags_from_title_and_channel() {
+        let tags = TrackTags::guess(
+            "Artist - Song (Official Music Video)",
+            "ArtistVEVO",
+            "2019-05-01T10:00:00Z",
+        );
+        assert_eq!(tags.artist, "Artist");
+        assert_eq!(tags.title, "Song");
+        assert_eq!(tags.year, "2019");
+
+        let tags = TrackTags::guess("Song [4K]", "Band - Topic", "");
+        assert_eq!(tags.artist, "Band");
+        assert_eq!(tags.title, "Song");
+        assert_eq!(tags.year, "");
+    }
+
+    #[test]
+    fn only_filled_fields_become_ffmpeg_arguments() {
+        let tags = TrackTags {
+            artist: "Artist".to_string(),
+            title: " Song ".to_string(),
+            track: "3/12".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            tags.ffmpeg_args(),
+            [
+                "-metadata",
+                "artist=Artist",
+                "-metadata",
+                "title=Song",
+                "-metadata",
+                "track=3/12"
+            ]
+        );
+        assert!(tags.validate().is_ok());
+        assert!(TrackTags::default().is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_year_and_track() {
+        let mut tags = TrackTags {
+            year: "19".to_string(),
+            ..Default::default()
+        };
+        assert!(tags.validate().is_err());
+        tags.year = "1999".to_string();
+        tags.track = "3/".to_string();
+        assert!(tags.validate().is_err());
+        tags.track = "three".to_string();
+        assert!(tags.validate().is_err());
+    }
+
+    #[test]
+    fn raw_aac_cannot_hold_tags() {
+        assert!(!holds_tags("AAC"));
+        assert!(holds_tags("m4a"));
+        assert!(holds_tags("mp3"));
+    }
+}
@@ -0,0 +1,185 @@
+use crate::metadata::Chapter;
+use std::ops::Range;
+
+// Tracklist in a video description, e.g.
+//   00:00 Intro
+//   1. 03:12 - Song B
+//   Song C [1:02:03]
+// The end of each track is left at 0; clean_chapters() fills it from the next start.
+pub fn parse_tracklist(description: &str) -> Vec<Chapter> {
+    let lines: Vec<Chapter> = description.lines().filter_map(parse_line).collect();
+    // longest ascending run; lone timestamps in the prose drop out
+    let mut best: Range<usize> = 0..0;
+    let mut start = 0;
+    for index in 1..=lines.len() {
+        let ascending =
+            index < lines.len() && lines[index].start_time > lines[index - 1].start_time;
+        if !ascending {
+            if index - start > best.len() {
+                best = start..index;
+            }
+            start = index;
+        }
+    }
+    if best.len() < 2 {
+        return Vec::new();
+    }
+    lines[best].to_vec()
+}
+
+fn parse_line(line: &str) -> Option<Chapter> {
+    let (range, start_time) = find_timestamp(line)?;
+    let mut range = range;
+    // "03:12 - 05:40 Song": the end of a range is not needed
+    let rest = &line[range.end..];
+    let trimmed = rest.trim_start_matches([' ', '-', '–', '~']);
+    if trimmed.len() < rest.len()
+        && let Some((end_range, _)) = find_timestamp(trimmed)
+        && end_range.start == 0
+    {
+        range.end = line.len() - trimmed.len() + end_range.end;
+    }
+    // "[03:12]" / "(03:12)"
+    let opens = line[..range.start].ends_with(['[', '(']);
+    let closes = line[range.end..].starts_with([']', ')']);
+    if opens && closes {
+        range = range.start - 1..range.end + 1;
+    }
+
+    let before = clean_title(&line[..range.start]);
+    let after = clean_title(&line[range.end..]);
+    // time in front (after a number) or at the end
+    let title = if after.is_empty() { before } else { after };
+    Some(Chapter {
+        start_time,
+        end_time: 0.0,
+        title: strip_numbering(&title).to_string(),
+    })
+}
+
+// First "M:SS", "MM:SS" or "H:MM:SS" in the line, with its byte range and seconds
+fn find_timestamp(line: &str) -> Option<(Range<usize>, f64)> {
+    let bytes = line.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        if !bytes[index].is_ascii_digit() || (index > 0 && bytes[index - 1].is_ascii_digit()) {
+            index += 1;
+            continue;
+        }
+        // collect digits and colons
+        let mut end = index;
+        while end < bytes.len() && (bytes[end].is_ascii_digit() || bytes[end] == b':') {
+            end += 1;
+        }
+        let candidate = line[index..end].trim_end_matches(':');
+        if let Some(seconds) = parse_timestamp(candidate) {
+            return Some((index..index + candidate.len(), seconds));
+        }
+        index = end;
+    }
+    None
+}
+
+// "3:12" -> 192, "1:02:03" -> 3723; seconds and minutes of an hour value must be below 60
+pub fn parse_timestamp(text: &str) -> Option<f64> {
+    let parts: Vec<&str> = text.trim().split(':').collect();
+    let valid = |part: &&str, max_len: usize| {
+        !part.is_empty() && part.len() <= max_len && part.chars().all(|c| c.is_ascii_digit())
+    };
+    let seconds = match parts.as_slice() {
+        [minutes, seconds] if valid(minutes, 3) && seconds.len() == 2 && valid(seconds, 2) => {
+            let seconds: u64 = seconds.parse().ok()?;
+            (seconds < 60).then_some(minutes.parse::<u64>().ok()? * 60 + seconds)?
+        }
+        [hours, minutes, seconds]
+            if valid(hours, 2)
+                && minutes.len() == 2
+                && seconds.len() == 2
+                && valid(minutes, 2)
+                && valid(seconds, 2) =>
+        {
+            let (minutes, seconds): (u64, u64) = (minutes.parse().ok()?, seconds.parse().ok()?);
+            (minutes < 60 && seconds < 60)
+                .then_some(hours.parse::<u64>().ok()? * 3_600 + minutes * 60 + seconds)?
+        }
+        _ => return None,
+    };
+    Some(seconds as f64)
+}
+
+fn clean_title(text: &str) -> String {
+    const SEPARATORS: [char; 10] = [' ', '-', '–', '—', '|', ':', '•', '·', '~', '\t'];
+    text.trim_matches(SEPARATORS).to_string()
+}
+
+// "01. Song", "1) Song" -> "Song"
+fn strip_numbering(title: &str) -> &str {
+    let digits = title.len() - title.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if !(1..=3).contains(&digits) {
+        return title;
+    }
+    let rest = &title[digits..];
+    match rest.strip_prefix(['.', ')']) {
+        Some(rest) if rest.starts_with(' ') => rest.trim_start(),
+        _ => title,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracks(description: &str) -> Vec<(f64, String)> {
+        parse_tracklist(description)
+            .into_iter()
+            .map(|chapter| (chapter.start_time, chapter.title))
+            .collect()
+    }
+
+    #[test]
+    fn reads_common_tracklist_styles() {
+        let description = "Best of 2019, recorded live at 20:15!\n\n\
+            Tracklist:\n\
+            00:00 Intro\n\
+            1. 03:12 - Song B\n\
+            [07:45] Artist - Song C\n\
+            Song D 12:01\n\
+            1:02:03 – 1:05:00 | Outro\n\n\
+            Follow us!";
+        assert_eq!(
+            tracks(description),
+            [
+                (0.0, "Intro".to_string()),
+                (192.0, "Song B".to_string()),
+                (465.0, "Artist - Song C".to_string()),
+                (721.0, "Song D".to_string()),
+                (3723.0, "Outro".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_single_or_unordered_timestamps() {
+        assert!(tracks("Premiere at 18:00, see you there").is_empty());
+        assert!(tracks("no times here\njust text").is_empty());
+        // comment with a timestamp after the list
+        let description = "0:00 One\n2:30 Two\n\nmy favourite part is 1:10";
+        assert_eq!(
+            tracks(description),
+            [(0.0, "One".to_string()), (150.0, "Two".to_string())]
+        );
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("3:12"), Some(192.0));
+        assert_eq!(parse_timestamp("75:00"), Some(4500.0));
+        assert_eq!(parse_timestamp("1:02:03"), Some(3723.0));
+        assert_eq!(parse_timestamp("3:75"), None);
+        assert_eq!(parse_timestamp("3:1"), None);
+        assert_eq!(parse_timestamp("1:60:00"), None);
+        assert_eq!(strip_numbering("01. Song"), "Song");
+        assert_eq!(strip_numbering("1) Song"), "Song");
+        assert_eq!(strip_numbering("99 Luftballons"), "99 Luftballons");
+    }
+}
@@ -1,17 +1,70 @@
+use crate::backend::{create_backend, effective_backend_kind};
+use crate::duration::format_duration;
+use crate::error::AppError;
+use crate::history::{DownloadHistory, HistoryEntry, format_date};
+use crate::logging::{LOG_ENV, log_dir, redact, set_level};
+use crate::metadata::PlaylistMetadata;
+use crate::output_template::{
+    APP_FIELDS, DEFAULT_OUTPUT_TEMPLATE, TemplateValues, YT_DLP_FIELDS, expand_path,
+    render_preview, resolve_app_fields, validate,
+};
+use crate::playlist::{PlaylistPosition, parse_ranges, playlist_id_from_url, select_entries};
+use crate::profiles::{
+    AudioCodec, DownloadMode, DownloadProfile, SAMPLE_RATES, VIDEO_HEIGHTS, VideoCodec,
+    VideoContainer, export_profiles, import_profiles,
+};
+use crate::progress::format_bytes;
+use crate::query_parser::{QueryError, parse_query};
+use crate::queue::{
+    DownloadJob, DownloadQueue, GroupId, GroupState, JobId, JobState, PlaylistGroup,
+};
 use crate::share::*;
-use crate::worker::{call_yt_api, download_from_dlp, set_video_durration};
-use eframe::egui::{self, Button, Color32, InnerResponse, Rect, Ui, vec2};
+use crate::split::{TrackOutput, TrackSplit, clean_chapters, supports_chapter_markers};
+use crate::tags::{TrackTags, holds_tags, is_media_file, media_files};
+use crate::tracklist::{parse_timestamp, parse_tracklist};
+use crate::worker::{
+    DEFAULT_API_BASE_URL, DownloadRequest, YouTubeClient, build_http_client, download_from_dlp,
+    fetch_metadata, fetch_playlist, resolve_api_base_url, write_tags,
+};
+use eframe::egui::{self, Button, Color32, InnerResponse, Rect, Ui, text::LayoutJob, vec2};
 use std::hash::Hash;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{Instrument, debug, error, info, info_span, warn};
 
 pub struct YtGUI {
     pub data: SearchResponse,
     pub search_item: Vec<SearchResponseMeta>,
     pub search_text: String,
+    pub last_query: SearchQuery,
+    pub show_filters: bool,
+    pub query_errors: Vec<QueryError>,
+    pub is_loading_more: bool,
+    // bumped for every new result list; replies of older searches are dropped
+    pub search_generation: u64,
+    pub listing: ListingSource,
+    pub parent_data: Option<SearchResponse>,
     pub side_width: f32,
     pub settings_state: SettingsState,
     pub image_loader_installed: bool,
     pub app_state: AppState,
     pub tokio_worker: TokioWorker,
+    pub http_client: reqwest::Client,
+    pub key_input: String,
+    pub key_check: KeyCheck,
+    pub download_queue: DownloadQueue,
+    pub toasts: Vec<AppError>,
+    pub profile_json: String,
+    // result of the last export/import
+    pub profile_status: Option<Result<String, String>>,
+    pub format_inspector: Option<FormatInspector>,
+    // index into data.items the output template preview is rendered for
+    pub template_preview: usize,
+    pub history: DownloadHistory,
+    pub playlist_dialog: Option<PlaylistDialog>,
+    pub tag_editor: Option<TagEditor>,
+    pub chapter_splitter: Option<ChapterSplitter>,
 }
 
 impl Default for YtGUI {
@@ -20,11 +73,31 @@ impl Default for YtGUI {
             data: SearchResponse::default(),
             search_item: Vec::new(),
             search_text: String::new(),
+            last_query: SearchQuery::default(),
+            show_filters: false,
+            query_errors: Vec::new(),
+            is_loading_more: false,
+            search_generation: 0,
+            listing: ListingSource::default(),
+            parent_data: None,
             side_width: 0.0,
             settings_state: SettingsState::default(),
             image_loader_installed: false,
             app_state: AppState::default(),
             tokio_worker: TokioWorker::default(),
+            http_client: build_http_client(),
+            key_input: String::new(),
+            key_check: KeyCheck::default(),
+            download_queue: DownloadQueue::default(),
+            toasts: Vec::new(),
+            profile_json: String::new(),
+            profile_status: None,
+            format_inspector: None,
+            template_preview: 0,
+            history: DownloadHistory::default(),
+            playlist_dialog: None,
+            tag_editor: None,
+            chapter_splitter: None,
         }
     }
 }
@@ -35,9 +108,11 @@ impl YtGUI {
             .storage
             .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
             .unwrap_or_default();
+        set_level(settings_state.log_level);
 
         Self {
             settings_state,
+            history: DownloadHistory::load(),
             ..Default::default()
         }
     }
@@ -52,34 +127,44 @@ impl YtGUI {
                     let spacing = (avaibale_width - (searchfield_width)) / 2.0;
 
                     ui.add_space(spacing);
+                    let base_query = self.base_query();
+                    let mut layouter = |ui: &Ui, buf: &dyn egui::TextBuffer, wrap_width: f32| {
+                        let errors = parse_query(buf.as_str(), base_query.clone()).errors;
+                        let mut job =
+                            highlight_errors(ui, buf.as_str(), errors.iter().map(|e| &e.span));
+                        job.wrap.max_width = wrap_width;
+                        ui.fonts(|f| f.layout_job(job))
+                    };
                     let searchfield = ui.add(
                         egui::TextEdit::singleline(&mut self.search_text)
                             .hint_text("🔍")
-                            .desired_width(searchfield_width),
+                            .desired_width(searchfield_width)
+                            .layouter(&mut layouter),
                     );
+                    let parsed = parse_query(&self.search_text, self.base_query());
+                    self.query_errors = parsed.errors;
 
-                    if !searchfield.has_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                        let search_string = self.search_text.clone();
-                        let max_reults = self.settings_state.max_results.clone();
-                        let rx = self.tokio_worker.tx.clone();
-                        let ctx_giver = ctx.clone();
-
-                        tokio::spawn(async move {
-                            let mut data = call_yt_api(search_string, max_reults).await.unwrap();
-
-                            let mut ex_video_ids: Vec<String> = Vec::new();
-                            for item in &data.items {
-                                if let Some(video_id) = &item.id.video_id {
-                                    ex_video_ids.push(video_id.clone());
-                                }
-                            }
-                            set_video_durration(ex_video_ids, &mut data).await.unwrap();
-
-                            rx.send(WorkerMessage::Data(data)).await.unwrap();
-                            ctx_giver.request_repaint();
-                        });
-
-                        self.search_text.clear();
+                    if !searchfield.has_focus()
+                        && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                        && self.query_errors.is_empty()
+                    {
+                        // pasted playlist link: show its entries instead of searching
+                        if let Some(playlist_id) = playlist_id_from_url(&self.search_text) {
+                            self.search_text.clear();
+                            self.open_drill_down(
+                                ctx,
+                                ListingSource::Playlist {
+                                    playlist_id,
+                                    title: "Playlist".to_string(),
+                                },
+                            );
+                        } else {
+                            self.last_query = parsed.query;
+                            self.listing = ListingSource::Search;
+                            self.parent_data = None;
+                            self.spawn_search(ctx, None);
+                            self.search_text.clear();
+                        }
                     }
 
                     let settings_button_margin: f32 = 10.0;
@@ -88,27 +173,107 @@ impl YtGUI {
                         if ui.add(Button::new("⚙")).clicked() {
                             self.app_state = AppState::Settings;
                         }
+                        let pending = self.download_queue.pending_count();
+                        let queue_label = if pending > 0 {
+                            format!("⬇ {pending}")
+                        } else {
+                            "⬇".to_string()
+                        };
+                        if ui.add(Button::new(queue_label)).clicked() {
+                            self.app_state = AppState::Queue;
+                        }
+                        ui.toggle_value(&mut self.show_filters, "filters");
+                        ui.toggle_value(
+                            &mut self.settings_state.hide_downloaded,
+                            "hide downloaded",
+                        );
+                        egui::ComboBox::from_id_salt("search_type")
+                            .selected_text(self.settings_state.search_type.label())
+                            .show_ui(ui, |ui| {
+                                for search_type in SearchType::ALL {
+                                    ui.selectable_value(
+                                        &mut self.settings_state.search_type,
+                                        search_type,
+                                        search_type.label(),
+                                    );
+                                }
+                            });
                     });
                 })
                 .response;
                 ui.allocate_space(vec2(ui.available_width(), 10.0));
+                if self.active_backend() != self.settings_state.selected_backend() {
+                    ui.colored_label(Color32::GRAY, "No API key – searching with yt-dlp");
+                }
+                for error in &self.query_errors {
+                    ui.colored_label(Color32::RED, &error.message);
+                }
+                if self.show_filters {
+                    self.filter_panel(ui);
+                }
 
                 ui.add_space(40.0);
+                let drill_title = match &self.listing {
+                    ListingSource::Search => None,
+                    ListingSource::Channel { title, .. }
+                    | ListingSource::Playlist { title, .. } => Some(title.clone()),
+                };
+                if let Some(title) = drill_title {
+                    ui.horizontal(|ui| {
+                        if ui.button("⬅ back").clicked() {
+                            self.close_drill_down();
+                        }
+                        ui.add_space(10.0);
+                        ui.heading(&title);
+                        if let ListingSource::Playlist { playlist_id, .. } = &self.listing {
+                            ui.add_space(10.0);
+                            if ui.button("⬇ download playlist").clicked() {
+                                self.open_playlist_dialog(playlist_id.clone(), title.clone());
+                            }
+                        }
+                    });
+                    ui.add_space(20.0);
+                }
+                let mut drill_down: Option<ListingSource> = None;
+                // video id, title, profile, force (ignore the archive)
+                let mut download: Option<(String, String, DownloadProfile, bool)> = None;
+                let mut forget: Option<String> = None;
+                let mut inspect: Option<(String, String)> = None;
+                let mut playlist_download: Option<(String, String)> = None;
+                let mut edit_tags: Option<(TagTarget, TrackTags)> = None;
+                let mut split: Option<(String, String, TrackTags)> = None;
                 ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
                     egui::ScrollArea::vertical()
                         .auto_shrink(false)
                         .show(ui, |ui| {
-                            for (index, item) in &mut self.data.items.iter().enumerate() {
-                                self.search_item.push(SearchResponseMeta {
-                                    is_enabled: true,
-                                    download_progress: 0,
-                                });
+                            let rows = self.data.items.iter().zip(&self.search_item);
+                            for (index, (item, row)) in rows.enumerate() {
+                                // the download state comes from the queue
+                                let job = row.job.and_then(|job| self.download_queue.get(job));
+                                let is_enabled = job.is_none_or(|job| job.state.is_finished());
+                                let progress = job
+                                    .filter(|job| job.state.is_active())
+                                    .and_then(|job| job.progress.clone());
+                                let error = job
+                                    .filter(|job| job.state == JobState::Failed)
+                                    .and_then(|job| job.error.as_ref());
+                                let in_library = item
+                                    .id
+                                    .video_id
+                                    .as_deref()
+                                    .is_some_and(|video_id| self.history.contains(video_id));
+                                // running downloads stay visible
+                                if in_library && is_enabled && self.settings_state.hide_downloaded {
+                                    continue;
+                                }
+                                let library_entry = item
+                                    .id
+                                    .video_id
+                                    .as_deref()
+                                    .and_then(|video_id| self.history.latest(video_id));
 
-                                if result_widget(
-                                    ui,
-                                    self.search_item[index].is_enabled,
-                                    index,
-                                    |ui| {
+                                let response =
+                                    result_widget(ui, is_enabled, (index, &row.result_id), |ui| {
                                         let scroll_bar: f32 = 10.0;
                                         ui.set_width(ui.available_width() - scroll_bar);
                                         ui.horizontal(|ui| {
@@ -124,63 +289,1541 @@ impl YtGUI {
                                                 .fit_to_exact_size(vec2(WIDTH, HEIGHT));
                                             ui.vertical(|ui| {
                                                 ui.add(image);
-                                                if let Some(duration) =
-                                                    item.video_durration.as_ref()
+                                                if item.snippet.live_broadcast_content == "live" {
+                                                    ui.colored_label(Color32::RED, "LIVE");
+                                                } else if let Some(duration) = item.video_durration
                                                 {
-                                                    ui.label(duration);
+                                                    ui.label(format_duration(duration));
                                                 }
                                             });
 
                                             ui.add_space(40.0);
                                             ui.vertical(|ui| {
+                                                match item.id.kind.as_str() {
+                                                    "youtube#channel" => {
+                                                        ui.colored_label(
+                                                            Color32::LIGHT_BLUE,
+                                                            "Channel",
+                                                        );
+                                                    }
+                                                    "youtube#playlist" => {
+                                                        ui.colored_label(
+                                                            Color32::LIGHT_BLUE,
+                                                            "Playlist",
+                                                        );
+                                                    }
+                                                    _ => {}
+                                                }
                                                 ui.label(&item.snippet.title);
                                                 ui.colored_label(
                                                     Color32::GRAY,
                                                     &item.snippet.channel_title,
                                                 );
+                                                if in_library {
+                                                    library_badge(ui, library_entry);
+                                                }
                                                 ui.add_space(10.0);
+                                                if let Some(progress) = &progress {
+                                                    download_progress_bar(ui, progress);
+                                                }
+                                                if let Some(error) = error {
+                                                    error_label(ui, error);
+                                                }
                                             });
                                         });
-                                    },
-                                )
-                                .response
-                                .clicked()
-                                    && self.search_item[index].is_enabled
-                                {
-                                    self.search_item[index].is_enabled = false;
-                                    if let Some(video_id) = &item.id.video_id {
-                                        let yt_link =
-                                            format!("https://www.youtube.com/watch?v={}", video_id);
-                                        println!("{}", &self.settings_state.download_path);
-                                        let path = self.settings_state.download_path.clone();
-                                        let yt_link = yt_link.clone();
-                                        let item_id = index.clone();
-
-                                        let tx = self.tokio_worker.tx.clone();
-                                        tokio::spawn(async move {
-                                            let error_handle = download_from_dlp(
-                                                tx, item_id, &yt_link, &path, "aac",
+                                    })
+                                    .response;
+
+                                // right click: download with another profile
+                                if is_enabled && let Some(video_id) = &item.id.video_id {
+                                    response.context_menu(|ui| {
+                                        if ui.button("🔍 formats").clicked() {
+                                            inspect = Some((
+                                                video_id.clone(),
+                                                item.snippet.title.clone(),
+                                            ));
+                                            ui.close();
+                                        }
+                                        ui.separator();
+                                        ui.label(if in_library {
+                                            "Download again as"
+                                        } else {
+                                            "Download as"
+                                        });
+                                        for profile in &self.settings_state.download_profiles {
+                                            let button = ui
+                                                .button(&profile.name)
+                                                .on_hover_text(profile.summary());
+                                            if button.clicked() {
+                                                download = Some((
+                                                    video_id.clone(),
+                                                    item.snippet.title.clone(),
+                                                    profile.clone(),
+                                                    in_library,
+                                                ));
+                                                ui.close();
+                                            }
+                                        }
+                                        let guess = || {
+                                            TrackTags::guess(
+                                                &item.snippet.title,
+                                                &item.snippet.channel_title,
+                                                &item.snippet.published_at,
                                             )
-                                            .await;
-                                            match error_handle {
-                                                Ok(()) => {}
-                                                Err(error) => {
-                                                    eprintln!("download failed with: {error}")
-                                                }
+                                        };
+                                        if !in_library
+                                            && ui.button("🏷 download with tags…").clicked()
+                                        {
+                                            let target = TagTarget::Download {
+                                                video_id: video_id.clone(),
+                                                title: item.snippet.title.clone(),
+                                            };
+                                            edit_tags = Some((target, guess()));
+                                            ui.close();
+                                        }
+                                        if ui.button("✂ split into tracks…").clicked() {
+                                            split = Some((
+                                                video_id.clone(),
+                                                item.snippet.title.clone(),
+                                                guess(),
+                                            ));
+                                            ui.close();
+                                        }
+                                        if in_library {
+                                            ui.separator();
+                                            // split tracks carry their own title and number,
+                                            // one set of tags would overwrite all of them
+                                            if let Some(entry) = library_entry
+                                                && entry
+                                                    .files
+                                                    .iter()
+                                                    .filter(|file| is_media_file(file))
+                                                    .count()
+                                                    == 1
+                                                && ui.button("🏷 edit tags…").clicked()
+                                            {
+                                                let target = TagTarget::Files(entry.files.clone());
+                                                edit_tags = Some((target, guess()));
+                                                ui.close();
+                                            }
+                                            if ui.button("remove from history").clicked() {
+                                                forget = Some(video_id.clone());
+                                                ui.close();
                                             }
+                                        }
+                                    });
+                                }
+                                if let Some(playlist_id) = &item.id.playlist_id {
+                                    response.context_menu(|ui| {
+                                        if ui.button("⬇ download playlist…").clicked() {
+                                            playlist_download = Some((
+                                                playlist_id.clone(),
+                                                item.snippet.title.clone(),
+                                            ));
+                                            ui.close();
+                                        }
+                                    });
+                                }
+                                if response.clicked() && is_enabled {
+                                    if let Some(playlist_id) = &item.id.playlist_id {
+                                        drill_down = Some(ListingSource::Playlist {
+                                            playlist_id: playlist_id.clone(),
+                                            title: item.snippet.title.clone(),
                                         });
+                                    } else if let (None, Some(channel_id)) =
+                                        (&item.id.video_id, &item.id.channel_id)
+                                    {
+                                        drill_down = Some(ListingSource::Channel {
+                                            channel_id: channel_id.clone(),
+                                            title: item.snippet.title.clone(),
+                                        });
+                                    } else if in_library {
+                                        info!(index, "already downloaded, not queued again");
+                                    } else if let Some(video_id) = &item.id.video_id {
+                                        download = Some((
+                                            video_id.clone(),
+                                            item.snippet.title.clone(),
+                                            self.settings_state.default_download_profile(),
+                                            false,
+                                        ));
                                     } else {
-                                        println!("Fehler Video_id nicht gefunden. Think");
+                                        warn!(index, "clicked result has no video id");
                                     }
-                                    println!("test am Index: {index}");
                                 }
                                 ui.add_space(20.0);
                             }
+
+                            // append the next page to the list
+                            if !self.data.next_page_token.is_empty() {
+                                ui.vertical_centered(|ui| {
+                                    let load_more = ui.add_enabled(
+                                        !self.is_loading_more,
+                                        Button::new("load more"),
+                                    );
+                                    if load_more.clicked() {
+                                        self.is_loading_more = true;
+                                        let page_token = self.data.next_page_token.clone();
+                                        self.spawn_search(ctx, Some(page_token));
+                                    }
+                                });
+                                ui.add_space(20.0);
+                            }
                         });
                     ui.allocate_space(ui.available_size());
                 });
+                if let Some(listing) = drill_down {
+                    self.open_drill_down(ctx, listing);
+                }
+                if let Some((video_id, title, profile, force)) = download {
+                    self.queue_download(video_id, title, profile, force);
+                }
+                if let Some((target, tags)) = edit_tags {
+                    self.open_tag_editor(target, tags);
+                }
+                if let Some((video_id, title, guess)) = split {
+                    self.open_chapter_splitter(ctx, video_id, title, guess);
+                }
+                if let Some(video_id) = forget {
+                    info!(video_id = %video_id, "removed from the download history");
+                    if let Err(error) = self.history.forget(&video_id) {
+                        self.show_toast(error.into());
+                    }
+                }
+                if let Some((playlist_id, title)) = playlist_download {
+                    self.open_playlist_dialog(playlist_id, title);
+                }
+                if let Some((video_id, title)) = inspect {
+                    self.open_format_inspector(ctx, video_id, title);
+                }
+            });
+        });
+    }
+
+    // Query from settings and filter panel; operators typed in the search box override it
+    pub fn base_query(&self) -> SearchQuery {
+        SearchQuery {
+            text: String::new(),
+            max_results: self.settings_state.max_results,
+            search_type: self.settings_state.search_type,
+            filters: self.settings_state.search_filters.clone(),
+            client_filter: ClientFilter::default(),
+        }
+    }
+
+    pub fn filter_panel(&mut self, ui: &mut egui::Ui) {
+        let filters = &mut self.settings_state.search_filters;
+        egui::Frame::new()
+            .fill(Color32::from_black_alpha(30))
+            .corner_radius(8)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+                ui.style_mut().spacing.item_spacing = egui::Vec2 { x: 8.0, y: 4.0 };
+                egui::Grid::new("search_filters")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Order");
+                        egui::ComboBox::from_id_salt("filter_order")
+                            .selected_text(filters.order.label())
+                            .show_ui(ui, |ui| {
+                                for order in SearchOrder::ALL {
+                                    ui.selectable_value(&mut filters.order, order, order.label());
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Published after");
+                        date_field(ui, &mut filters.published_after);
+                        ui.end_row();
+
+                        ui.label("Published before");
+                        date_field(ui, &mut filters.published_before);
+                        ui.end_row();
+
+                        ui.label("Duration");
+                        egui::ComboBox::from_id_salt("filter_duration")
+                            .selected_text(filters.video_duration.label())
+                            .show_ui(ui, |ui| {
+                                for duration in VideoDurationFilter::ALL {
+                                    ui.selectable_value(
+                                        &mut filters.video_duration,
+                                        duration,
+                                        duration.label(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Region");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut filters.region_code)
+                                .hint_text("DE")
+                                .desired_width(60.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Language");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut filters.relevance_language)
+                                .hint_text("de")
+                                .desired_width(60.0),
+                        );
+                        ui.end_row();
+
+                        ui.label("Safe search");
+                        egui::ComboBox::from_id_salt("filter_safe_search")
+                            .selected_text(filters.safe_search.label())
+                            .show_ui(ui, |ui| {
+                                for safe_search in SafeSearch::ALL {
+                                    ui.selectable_value(
+                                        &mut filters.safe_search,
+                                        safe_search,
+                                        safe_search.label(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Music only");
+                        ui.checkbox(&mut filters.music_only, "");
+                        ui.end_row();
+                    });
+                if ui.button("reset filters").clicked() {
+                    *filters = SearchFilters::default();
+                }
+            });
+        ui.add_space(10.0);
+    }
+
+    // Row view model for the current results; rows of videos with a download keep their job
+    fn row_for(&self, item: &SearchItem) -> SearchResponseMeta {
+        SearchResponseMeta {
+            result_id: item.id.result_id().to_string(),
+            job: item
+                .id
+                .video_id
+                .as_deref()
+                .and_then(|video_id| self.download_queue.latest_job_for(video_id)),
+        }
+    }
+
+    // Replaces the result list, or appends to it for a next page
+    fn receive_results(&mut self, generation: u64, data: SearchResponse, next_page: bool) {
+        if generation != self.search_generation {
+            debug!(
+                generation,
+                current = self.search_generation,
+                "stale search reply dropped"
+            );
+            return;
+        }
+        if !next_page {
+            self.data = data;
+            self.rebuild_rows();
+            return;
+        }
+        self.is_loading_more = false;
+        let rows: Vec<SearchResponseMeta> =
+            data.items.iter().map(|item| self.row_for(item)).collect();
+        self.search_item.extend(rows);
+        self.data.next_page_token = data.next_page_token;
+        self.data.page_info = data.page_info;
+        self.data.items.extend(data.items);
+    }
+
+    fn rebuild_rows(&mut self) {
+        self.search_item = self
+            .data
+            .items
+            .iter()
+            .map(|item| self.row_for(item))
+            .collect();
+    }
+
+    pub fn queue_download(
+        &mut self,
+        video_id: String,
+        title: String,
+        profile: DownloadProfile,
+        force: bool,
+    ) -> JobId {
+        let profile_name = profile.name.clone();
+        let job = self
+            .download_queue
+            .push(video_id.clone(), title, profile, force);
+        info!(job, video_id = %video_id, profile = %profile_name, force, "download queued");
+        for row in self
+            .search_item
+            .iter_mut()
+            .filter(|row| row.result_id == video_id)
+        {
+            row.job = Some(job);
+        }
+        job
+    }
+
+    // Adds a finished job to the history; no files means yt-dlp found it in its archive
+    fn record_download(&mut self, job_id: JobId, files: Vec<PathBuf>) {
+        let Some(job) = self.download_queue.get(job_id) else {
+            return;
+        };
+        let video_id = job.video_id.clone();
+        if files.is_empty() {
+            info!(job = job_id, video_id = %video_id, "skipped, already in the archive");
+            self.history.mark_archived(video_id);
+            return;
+        }
+        let entry = HistoryEntry::new(
+            video_id,
+            job.title.clone(),
+            files.clone(),
+            job.profile.name.clone(),
+            job.profile.summary(),
+        );
+        self.download_queue.set_files(job_id, files);
+        if let Err(error) = self.history.record(entry) {
+            error!(%error, "download history could not be saved");
+            self.show_toast(error.into());
+        }
+    }
+
+    pub fn open_playlist_dialog(&mut self, playlist_id: String, title: String) {
+        self.playlist_dialog = Some(PlaylistDialog {
+            playlist_id,
+            title,
+            range: String::new(),
+            skip_downloaded: true,
+            profile: self.settings_state.default_download_profile().name,
+        });
+    }
+
+    // Range, profile and archive options before the entries are listed
+    pub fn render_playlist_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.playlist_dialog else {
+            return;
+        };
+        let mut open = true;
+        let mut start = false;
+        let range = parse_ranges(&dialog.range);
+        egui::Window::new("Download playlist")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(&dialog.title);
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.label("Entries");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut dialog.range)
+                            .hint_text("all, or e.g. 1-10, 15, 20-")
+                            .desired_width(180.0),
+                    );
+                });
+                if let Err(message) = &range {
+                    ui.colored_label(Color32::RED, message);
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Profile");
+                    egui::ComboBox::from_id_salt("playlist_profile")
+                        .selected_text(&dialog.profile)
+                        .show_ui(ui, |ui| {
+                            for profile in &self.settings_state.download_profiles {
+                                ui.selectable_value(
+                                    &mut dialog.profile,
+                                    profile.name.clone(),
+                                    &profile.name,
+                                )
+                                .on_hover_text(profile.summary());
+                            }
+                        });
+                });
+                ui.checkbox(
+                    &mut dialog.skip_downloaded,
+                    "skip entries downloaded before",
+                );
+                ui.add_space(10.0);
+                if ui
+                    .add_enabled(range.is_ok(), Button::new("⬇ download"))
+                    .clicked()
+                {
+                    start = true;
+                }
+            });
+        if start {
+            let dialog = self.playlist_dialog.take().unwrap();
+            self.start_playlist_download(ctx, dialog);
+        } else if !open {
+            self.playlist_dialog = None;
+        }
+    }
+
+    // Creates the group in the queue and lets yt-dlp list the entries
+    fn start_playlist_download(&mut self, ctx: &egui::Context, dialog: PlaylistDialog) {
+        let profile = self.settings_state.profile_named(&dialog.profile);
+        let group = self.download_queue.push_group(
+            dialog.playlist_id.clone(),
+            dialog.title,
+            profile,
+            dialog.range,
+            dialog.skip_downloaded,
+        );
+        info!(group, playlist_id = %dialog.playlist_id, "playlist download requested");
+
+        let tx = self.tokio_worker.tx.clone();
+        let ctx = ctx.clone();
+        let span = info_span!("playlist", group, playlist_id = %dialog.playlist_id);
+        tokio::spawn(
+            async move {
+                let result = fetch_playlist(&dialog.playlist_id).await;
+                match &result {
+                    Ok(playlist) => info!(entries = playlist.entries.len(), "playlist listed"),
+                    Err(error) => error!(%error, "playlist could not be listed"),
+                }
+                tx.send(WorkerMessage::PlaylistEntries { group, result })
+                    .await
+                    .unwrap();
+                ctx.request_repaint();
+            }
+            .instrument(span),
+        );
+    }
+
+    // Turns the listed entries into child jobs, honouring range and archive
+    fn fill_playlist_group(&mut self, group_id: GroupId, playlist: PlaylistMetadata) {
+        let Some(group) = self.download_queue.group(group_id) else {
+            return;
+        };
+        // the range was already checked in the dialog
+        let ranges = parse_ranges(&group.range).unwrap_or_default();
+        let skip_downloaded = group.skip_downloaded;
+        let playlist_id = group.playlist_id.clone();
+        let (selected, skipped) = select_entries(&playlist.entries, &ranges, |video_id| {
+            skip_downloaded && self.history.contains(video_id)
+        });
+        let count = playlist.entries.len();
+        let entries: Vec<(String, String, PlaylistPosition)> = selected
+            .into_iter()
+            .map(|(index, entry)| {
+                let position = PlaylistPosition {
+                    playlist_id: playlist_id.clone(),
+                    title: playlist.title.clone(),
+                    index,
+                    count,
+                };
+                (entry.id.clone(), entry.title.clone(), position)
+            })
+            .collect();
+        info!(
+            group = group_id,
+            queued = entries.len(),
+            skipped,
+            "playlist entries queued"
+        );
+        self.download_queue
+            .fill_group(group_id, playlist.title.clone(), entries, skipped);
+    }
+
+    pub fn open_tag_editor(&mut self, mut target: TagTarget, tags: TrackTags) {
+        // downloads can come with a .cue sheet next to the audio; ffmpeg can't tag that
+        if let TagTarget::Files(files) = &mut target {
+            files.retain(|file| is_media_file(file));
+        }
+        self.tag_editor = Some(TagEditor {
+            target,
+            tags,
+            profile: self.settings_state.default_download_profile().name,
+            folder: Vec::new(),
+            writing: false,
+        });
+    }
+
+    // Editor for files that are already in the download folder; starts with the first one
+    pub fn open_folder_tag_editor(&mut self) {
+        let folder = media_files(&expand_path(&self.settings_state.download_path));
+        let first = folder.first().cloned();
+        self.open_tag_editor(
+            TagTarget::Files(first.iter().cloned().collect()),
+            first.as_deref().map(guess_from_file).unwrap_or_default(),
+        );
+        if let Some(editor) = &mut self.tag_editor {
+            editor.folder = folder;
+        }
+    }
+
+    // Artist/title/album/year/track fields; downloads the video or rewrites the files
+    pub fn render_tag_editor(&mut self, ctx: &egui::Context) {
+        let Some(editor) = &mut self.tag_editor else {
+            return;
+        };
+        let mut open = true;
+        let mut apply = false;
+        let extension = match &editor.target {
+            TagTarget::Download { .. } => self
+                .settings_state
+                .profile_named(&editor.profile)
+                .extension()
+                .to_string(),
+            TagTarget::Files(files) => files
+                .iter()
+                .filter_map(|file| file.extension())
+                .map(|ext| ext.to_string_lossy().into_owned())
+                .find(|ext| !holds_tags(ext))
+                .unwrap_or_default(),
+        };
+        let valid = editor.tags.validate().and_then(|()| {
+            if holds_tags(&extension) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} files cannot hold tags, pick an M4A profile",
+                    extension.to_uppercase()
+                ))
+            }
+        });
+        egui::Window::new("Tags")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                match &mut editor.target {
+                    TagTarget::Download { title, .. } => {
+                        ui.label(title.as_str());
+                    }
+                    TagTarget::Files(files) if !editor.folder.is_empty() => {
+                        let selected = files.first().cloned();
+                        let name = |path: &PathBuf| {
+                            path.file_name()
+                                .map(|name| name.to_string_lossy().into_owned())
+                                .unwrap_or_default()
+                        };
+                        egui::ComboBox::from_id_salt("tag_file")
+                            .selected_text(selected.as_ref().map(name).unwrap_or_default())
+                            .width(300.0)
+                            .show_ui(ui, |ui| {
+                                for file in &editor.folder {
+                                    if ui
+                                        .selectable_label(
+                                            selected.as_ref() == Some(file),
+                                            name(file),
+                                        )
+                                        .clicked()
+                                    {
+                                        *files = vec![file.clone()];
+                                        editor.tags = guess_from_file(file);
+                                    }
+                                }
+                            });
+                    }
+                    TagTarget::Files(files) => {
+                        if files.is_empty() {
+                            ui.colored_label(
+                                Color32::GRAY,
+                                "No audio or video files in the download folder",
+                            );
+                        }
+                        for file in files.iter() {
+                            if let Some(name) = file.file_name() {
+                                ui.colored_label(Color32::GRAY, name.to_string_lossy())
+                                    .on_hover_text(file.display().to_string());
+                            }
+                        }
+                    }
+                }
+                ui.add_space(10.0);
+                egui::Grid::new("tag_fields")
+                    .num_columns(2)
+                    .spacing(vec2(10.0, 6.0))
+                    .show(ui, |ui| {
+                        let tags = &mut editor.tags;
+                        for (label, value, hint) in [
+                            ("Artist", &mut tags.artist, ""),
+                            ("Title", &mut tags.title, ""),
+                            ("Album", &mut tags.album, ""),
+                            ("Year", &mut tags.year, "2019"),
+                            ("Track", &mut tags.track, "3 or 3/12"),
+                        ] {
+                            ui.label(label);
+                            ui.add(
+                                egui::TextEdit::singleline(value)
+                                    .hint_text(hint)
+                                    .desired_width(250.0),
+                            );
+                            ui.end_row();
+                        }
+                    });
+                if let Err(message) = &valid {
+                    ui.colored_label(Color32::RED, message);
+                }
+                ui.colored_label(Color32::GRAY, "empty fields keep what the file has");
+                ui.add_space(10.0);
+                match &editor.target {
+                    TagTarget::Download { .. } => {
+                        ui.horizontal(|ui| {
+                            ui.label("Profile");
+                            egui::ComboBox::from_id_salt("tag_profile")
+                                .selected_text(&editor.profile)
+                                .show_ui(ui, |ui| {
+                                    for profile in &self.settings_state.download_profiles {
+                                        ui.selectable_value(
+                                            &mut editor.profile,
+                                            profile.name.clone(),
+                                            &profile.name,
+                                        )
+                                        .on_hover_text(profile.summary());
+                                    }
+                                });
+                        });
+                        ui.add_space(10.0);
+                        if ui
+                            .add_enabled(valid.is_ok(), Button::new("⬇ download"))
+                            .clicked()
+                        {
+                            apply = true;
+                        }
+                    }
+                    TagTarget::Files(files) => {
+                        let can_write = valid.is_ok()
+                            && !files.is_empty()
+                            && !editor.tags.is_empty()
+                            && !editor.writing;
+                        ui.horizontal(|ui| {
+                            if ui
+                                .add_enabled(can_write, Button::new("🏷 write tags"))
+                                .clicked()
+                            {
+                                apply = true;
+                            }
+                            if editor.writing {
+                                ui.spinner();
+                            }
+                        });
+                    }
+                }
+            });
+        if !open {
+            self.tag_editor = None;
+        } else if apply {
+            self.apply_tag_editor(ctx);
+        }
+    }
+
+    fn apply_tag_editor(&mut self, ctx: &egui::Context) {
+        let Some(editor) = &mut self.tag_editor else {
+            return;
+        };
+        match &editor.target {
+            TagTarget::Download { video_id, title } => {
+                let (video_id, title) = (video_id.clone(), title.clone());
+                let tags = editor.tags.clone();
+                let profile = self.settings_state.profile_named(&editor.profile);
+                self.tag_editor = None;
+                let job = self.queue_download(video_id, title, profile, false);
+                self.download_queue.set_tags(job, tags);
+            }
+            TagTarget::Files(files) => {
+                editor.writing = true;
+                let files = files.clone();
+                let tags = editor.tags.clone();
+                let tx = self.tokio_worker.tx.clone();
+                let ctx = ctx.clone();
+                let span = info_span!("tags", files = files.len());
+                tokio::spawn(
+                    async move {
+                        let result = write_tags(&files, &tags).await;
+                        tx.send(WorkerMessage::TagsWritten { files, result })
+                            .await
+                            .unwrap();
+                        ctx.request_repaint();
+                    }
+                    .instrument(span),
+                );
+            }
+        }
+    }
+
+    // Opens the format table and asks yt-dlp for the metadata
+    pub fn open_format_inspector(&mut self, ctx: &egui::Context, video_id: String, title: String) {
+        let profile = self.settings_state.default_download_profile().name;
+        self.format_inspector = Some(FormatInspector {
+            video_id: video_id.clone(),
+            title,
+            metadata: None,
+            profile,
+        });
+        self.app_state = AppState::Formats;
+        self.spawn_metadata_fetch(ctx, video_id);
+    }
+
+    // Track preview of one video; chapters and description come with the yt-dlp metadata
+    pub fn open_chapter_splitter(
+        &mut self,
+        ctx: &egui::Context,
+        video_id: String,
+        title: String,
+        guess: TrackTags,
+    ) {
+        self.chapter_splitter = Some(ChapterSplitter {
+            video_id: video_id.clone(),
+            title,
+            guess,
+            metadata: None,
+            chapters: Vec::new(),
+            tracklist: Vec::new(),
+            source: TrackSource::Chapters,
+            split: TrackSplit::default(),
+            profile: self.settings_state.default_download_profile().name,
+        });
+        self.app_state = AppState::Chapters;
+        self.spawn_metadata_fetch(ctx, video_id);
+    }
+
+    // yt-dlp -J in the background, answered with WorkerMessage::Metadata
+    fn spawn_metadata_fetch(&self, ctx: &egui::Context, video_id: String) {
+        let tx = self.tokio_worker.tx.clone();
+        let ctx = ctx.clone();
+        let span = info_span!("metadata", video_id = %video_id);
+        tokio::spawn(
+            async move {
+                let url = format!("https://www.youtube.com/watch?v={video_id}");
+                let result = fetch_metadata(&url).await.map(Box::new);
+                match &result {
+                    Ok(metadata) => info!(
+                        formats = metadata.formats.len(),
+                        chapters = metadata.chapters.len(),
+                        "metadata loaded"
+                    ),
+                    Err(error) => error!(%error, "metadata failed"),
+                }
+                tx.send(WorkerMessage::Metadata { video_id, result })
+                    .await
+                    .unwrap();
+                ctx.request_repaint();
+            }
+            .instrument(span),
+        );
+    }
+
+    // Track list with titles, start times and album tags; queues one download that is cut afterwards
+    pub fn render_chapters(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("back").clicked() {
+                self.app_state = AppState::App;
+            }
+            ui.add_space(10.0);
+            ui.heading("Split into tracks");
+        });
+        ui.add_space(20.0);
+        let Some(splitter) = &mut self.chapter_splitter else {
+            return;
+        };
+        ui.label(&splitter.title);
+        match &splitter.metadata {
+            None => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("asking yt-dlp…");
+                });
+                return;
+            }
+            Some(Err(error)) => {
+                error_label(ui, error);
+                return;
+            }
+            Some(Ok(metadata)) if metadata.is_live() => {
+                ui.colored_label(Color32::RED, "Live streams cannot be split");
+                return;
+            }
+            Some(Ok(_)) if splitter.chapters.is_empty() && splitter.tracklist.is_empty() => {
+                ui.colored_label(
+                    Color32::GRAY,
+                    "This video has no chapters and no tracklist in its description.",
+                );
+                return;
+            }
+            Some(Ok(_)) => {}
+        }
+        let duration = match &splitter.metadata {
+            Some(Ok(metadata)) => metadata.duration,
+            _ => None,
+        };
+        ui.add_space(10.0);
+        if !splitter.chapters.is_empty() && !splitter.tracklist.is_empty() {
+            ui.horizontal(|ui| {
+                ui.label("Tracks from");
+                for source in [TrackSource::Chapters, TrackSource::Description] {
+                    if ui
+                        .selectable_value(&mut splitter.source, source, source.label())
+                        .changed()
+                    {
+                        splitter.split.chapters = match source {
+                            TrackSource::Chapters => splitter.chapters.clone(),
+                            TrackSource::Description => splitter.tracklist.clone(),
+                        };
+                    }
+                }
+            });
+        }
+        if splitter.source == TrackSource::Description {
+            ui.colored_label(
+                Color32::GRAY,
+                "read from the description – check the start times before downloading",
+            );
+        }
+        ui.add_space(10.0);
+
+        let split = &mut splitter.split;
+        egui::Grid::new("split_tags")
+            .num_columns(2)
+            .spacing(vec2(10.0, 6.0))
+            .show(ui, |ui| {
+                ui.label("Artist");
+                ui.text_edit_singleline(&mut split.artist);
+                ui.end_row();
+                ui.label("Album");
+                ui.text_edit_singleline(&mut split.album)
+                    .on_hover_text("also the name of the folder the tracks go into");
+                ui.end_row();
+                ui.label("Year");
+                ui.add(egui::TextEdit::singleline(&mut split.year).hint_text("2019"));
+                ui.end_row();
             });
+        let year_error = TrackTags {
+            year: split.year.clone(),
+            ..Default::default()
+        }
+        .validate()
+        .err();
+        if let Some(message) = &year_error {
+            ui.colored_label(Color32::RED, message);
+        }
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label("Profile");
+            egui::ComboBox::from_id_salt("split_profile")
+                .selected_text(&splitter.profile)
+                .show_ui(ui, |ui| {
+                    for profile in &self.settings_state.download_profiles {
+                        ui.selectable_value(
+                            &mut splitter.profile,
+                            profile.name.clone(),
+                            &profile.name,
+                        )
+                        .on_hover_text(profile.summary());
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Output");
+            for output in [TrackOutput::Files, TrackOutput::SingleFile] {
+                ui.selectable_value(&mut split.output, output, output.label());
+            }
+        });
+        let extension = self
+            .settings_state
+            .profile_named(&splitter.profile)
+            .extension();
+        let markers_supported = supports_chapter_markers(extension);
+        let tags_supported = holds_tags(extension);
+        if !tags_supported {
+            ui.colored_label(
+                Color32::RED,
+                format!(
+                    "{} files cannot hold tags, pick an M4A profile",
+                    extension.to_uppercase()
+                ),
+            );
+        }
+        if split.output == TrackOutput::SingleFile {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut split.cue_sheet, ".cue sheet")
+                    .on_hover_text("track list next to the file, read by foobar2000, VLC and co.");
+                ui.add_enabled(
+                    markers_supported,
+                    egui::Checkbox::new(&mut split.chapter_markers, "chapter markers"),
+                )
+                .on_disabled_hover_text(format!(
+                    "{} files cannot hold chapters",
+                    extension.to_uppercase()
+                ));
+            });
+        }
+        let has_output = match split.output {
+            TrackOutput::Files => tags_supported,
+            TrackOutput::SingleFile => {
+                split.cue_sheet || (split.chapter_markers && markers_supported)
+            }
+        };
+        ui.add_space(10.0);
+        let label = match split.output {
+            TrackOutput::Files => format!("✂ download {} tracks", split.chapters.len()),
+            TrackOutput::SingleFile => {
+                format!("⬇ download with {} tracks", split.chapters.len())
+            }
+        };
+        let start = ui
+            .add_enabled(
+                year_error.is_none()
+                    && !split.album.trim().is_empty()
+                    && !split.chapters.is_empty()
+                    && has_output,
+                Button::new(label),
+            )
+            .clicked();
+        if start {
+            self.start_split_download();
+            return;
+        }
+        ui.add_space(20.0);
+
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                let Some(splitter) = &mut self.chapter_splitter else {
+                    return;
+                };
+                let mut retime = false;
+                let mut remove = None;
+                egui::Grid::new("split_chapters")
+                    .num_columns(5)
+                    .striped(true)
+                    .spacing(vec2(20.0, 6.0))
+                    .show(ui, |ui| {
+                        ui.strong("#");
+                        ui.strong("start");
+                        ui.strong("length");
+                        ui.strong("title");
+                        ui.end_row();
+                        for (index, chapter) in splitter.split.chapters.iter_mut().enumerate() {
+                            ui.label((index + 1).to_string());
+                            let start = egui::DragValue::new(&mut chapter.start_time)
+                                .range(0.0..=duration.unwrap_or(f64::MAX))
+                                .speed(1.0)
+                                .custom_formatter(|seconds, _| {
+                                    format_duration(Duration::from_secs_f64(seconds))
+                                })
+                                .custom_parser(parse_timestamp);
+                            // recompute the ends only after editing
+                            let start = ui.add(start);
+                            retime |= start.drag_stopped() || start.lost_focus();
+                            ui.label(format_duration(Duration::from_secs_f64(
+                                (chapter.end_time - chapter.start_time).max(0.0),
+                            )));
+                            ui.add(
+                                egui::TextEdit::singleline(&mut chapter.title).desired_width(300.0),
+                            );
+                            if ui
+                                .small_button("🗑")
+                                .on_hover_text("remove; the track before runs on")
+                                .clicked()
+                            {
+                                remove = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if let Some(index) = remove {
+                    splitter.split.chapters.remove(index);
+                    retime = true;
+                }
+                if retime {
+                    splitter.split.chapters = clean_chapters(&splitter.split.chapters, duration);
+                }
+            });
+    }
+
+    fn start_split_download(&mut self) {
+        let Some(splitter) = self.chapter_splitter.take() else {
+            return;
+        };
+        let profile = self.settings_state.profile_named(&splitter.profile);
+        info!(
+            video_id = %splitter.video_id,
+            tracks = splitter.split.chapters.len(),
+            output = ?splitter.split.output,
+            "split download requested"
+        );
+        // tracks or a CUE are something else than the whole mix already downloaded
+        let job = self.queue_download(splitter.video_id, splitter.title, profile, true);
+        self.download_queue.set_split(job, splitter.split);
+        self.app_state = AppState::Queue;
+    }
+
+    // Table of all formats yt-dlp offers; "download" queues exactly that format
+    pub fn render_formats(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("back").clicked() {
+                self.app_state = AppState::App;
+            }
+            ui.add_space(10.0);
+            ui.heading("Formats");
+        });
+        ui.add_space(20.0);
+        let Some(inspector) = &mut self.format_inspector else {
+            return;
+        };
+        ui.label(&inspector.title);
+        let metadata = match &inspector.metadata {
+            None => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("asking yt-dlp…");
+                });
+                return;
+            }
+            Some(Err(error)) => {
+                error_label(ui, error);
+                return;
+            }
+            Some(Ok(metadata)) => metadata,
+        };
+
+        ui.horizontal(|ui| {
+            if let Some(duration) = metadata.duration {
+                ui.colored_label(
+                    Color32::GRAY,
+                    format_duration(Duration::from_secs_f64(duration)),
+                );
+            }
+            if !metadata.chapters.is_empty() {
+                ui.colored_label(
+                    Color32::GRAY,
+                    format!("{} chapters", metadata.chapters.len()),
+                );
+            }
+            if metadata.is_live() {
+                ui.colored_label(Color32::RED, "live – downloads may not work");
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("post-processing with");
+            egui::ComboBox::from_id_salt("format_profile")
+                .selected_text(&inspector.profile)
+                .show_ui(ui, |ui| {
+                    for profile in &self.settings_state.download_profiles {
+                        ui.selectable_value(
+                            &mut inspector.profile,
+                            profile.name.clone(),
+                            &profile.name,
+                        )
+                        .on_hover_text(profile.summary());
+                    }
+                });
+        });
+        ui.add_space(10.0);
+
+        let mut picked = None;
+        egui_extras::TableBuilder::new(ui)
+            .striped(true)
+            .auto_shrink(false)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .columns(egui_extras::Column::auto(), 9)
+            .column(egui_extras::Column::remainder())
+            .header(20.0, |mut header| {
+                for title in [
+                    "id",
+                    "ext",
+                    "resolution",
+                    "fps",
+                    "video",
+                    "audio",
+                    "bitrate",
+                    "size",
+                    "note",
+                    "",
+                ] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|mut body| {
+                for format in metadata.media_formats() {
+                    body.row(20.0, |mut row| {
+                        let optional = |value: &Option<String>| {
+                            value.clone().unwrap_or_else(|| "-".to_string())
+                        };
+                        let cells = [
+                            format.format_id.clone(),
+                            optional(&format.ext),
+                            format.resolution(),
+                            format
+                                .fps
+                                .map(|fps| format!("{fps:.0}"))
+                                .unwrap_or_default(),
+                            optional(&format.vcodec),
+                            optional(&format.acodec),
+                            format
+                                .tbr
+                                .or(format.abr)
+                                .or(format.vbr)
+                                .map(|kbps| format!("{kbps:.0} kbit/s"))
+                                .unwrap_or_default(),
+                            match format.size() {
+                                Some(size) if format.size_is_exact() => format_bytes(size as f64),
+                                Some(size) => format!("≈{}", format_bytes(size as f64)),
+                                None => String::new(),
+                            },
+                            optional(&format.format_note),
+                        ];
+                        for cell in cells {
+                            row.col(|ui| {
+                                ui.label(cell);
+                            });
+                        }
+                        row.col(|ui| {
+                            let spec = format.download_spec();
+                            let mut hover = format!("yt-dlp -f {spec}");
+                            if let Some(size) = metadata.estimated_size(&spec) {
+                                hover.push_str(&format!(", about {}", format_bytes(size as f64)));
+                            }
+                            if ui.button("download").on_hover_text(hover).clicked() {
+                                picked = Some((spec, format.has_video()));
+                            }
+                        });
+                    });
+                }
+            });
+
+        if let Some((spec, has_video)) = picked {
+            let mut profile = self.settings_state.profile_named(&inspector.profile);
+            // the kind of format decides whether audio is extracted
+            profile.mode = if has_video {
+                DownloadMode::Video
+            } else {
+                DownloadMode::Audio
+            };
+            profile.format_id = Some(spec);
+            let video_id = inspector.video_id.clone();
+            let title = inspector.title.clone();
+            // a deliberately picked format is downloaded even if the video is already there
+            let force = self.history.contains(&video_id);
+            self.queue_download(video_id, title, profile, force);
+        }
+    }
+
+    pub fn open_drill_down(&mut self, ctx: &egui::Context, listing: ListingSource) {
+        // remember the search results so "back" returns without a new request
+        if self.parent_data.is_none() {
+            self.parent_data = Some(std::mem::take(&mut self.data));
+        } else {
+            self.data = SearchResponse::default();
+        }
+        self.listing = listing;
+        self.rebuild_rows();
+        self.spawn_search(ctx, None);
+    }
+
+    pub fn close_drill_down(&mut self) {
+        if let Some(parent_data) = self.parent_data.take() {
+            self.data = parent_data;
+            self.rebuild_rows();
+        }
+        self.listing = ListingSource::Search;
+        self.is_loading_more = false;
+        // pages of the drill-down still on their way belong to a list that is gone
+        self.search_generation += 1;
+    }
+
+    // Loads the current listing (search or drill-down); with a page_token it appends
+    pub fn spawn_search(&mut self, ctx: &egui::Context, page_token: Option<String>) {
+        if page_token.is_none() {
+            self.search_generation += 1;
+            self.is_loading_more = false;
+        }
+        let generation = self.search_generation;
+        let query = self.last_query.clone();
+        let max_reults = self.settings_state.max_results;
+        let listing = self.listing.clone();
+        let backend = create_backend(
+            self.active_backend(),
+            &self.settings_state,
+            self.http_client.clone(),
+        );
+        let rx = self.tokio_worker.tx.clone();
+        let ctx_giver = ctx.clone();
+        let span = info_span!(
+            "search",
+            query = %query.text,
+            backend = ?self.active_backend(),
+            page = page_token.as_deref().unwrap_or("first")
+        );
+
+        let task = async move {
+            let is_next_page = page_token.is_some();
+            // the filter belongs to the typed query, drill-downs list everything
+            let client_filter = match listing {
+                ListingSource::Search => query.client_filter.clone(),
+                _ => ClientFilter::default(),
+            };
+            let result = match listing {
+                ListingSource::Search => backend.search(query, page_token).await,
+                ListingSource::Playlist { playlist_id, .. } => {
+                    backend
+                        .playlist_videos(playlist_id, max_reults, page_token)
+                        .await
+                }
+                ListingSource::Channel { channel_id, .. } => {
+                    backend
+                        .channel_videos(channel_id, max_reults, page_token)
+                        .await
+                }
+            };
+            let message = match result {
+                Ok(mut data) => {
+                    if !client_filter.is_empty() {
+                        data.items.retain(|item| client_filter.matches(item));
+                    }
+                    info!(results = data.items.len(), "search finished");
+                    if is_next_page {
+                        WorkerMessage::NextPage { generation, data }
+                    } else {
+                        WorkerMessage::Data { generation, data }
+                    }
+                }
+                Err(error) => {
+                    error!(%error, "search failed");
+                    WorkerMessage::Error { job: None, error }
+                }
+            };
+            rx.send(message).await.unwrap();
+            ctx_giver.request_repaint();
+        };
+        tokio::spawn(task.instrument(span));
+    }
+
+    // Starts queued downloads as long as the limit is not reached
+    pub fn start_queued_downloads(&mut self) {
+        let max_concurrent = self.settings_state.max_concurrent_downloads;
+        for start in self.download_queue.start_next(max_concurrent) {
+            if let Some(error) = validate(&self.settings_state.output_template).first() {
+                let error = AppError::Other(format!(
+                    "The file name template is invalid ({}). Fix it in the settings.",
+                    error.message
+                ));
+                self.download_queue
+                    .finish(start.id, JobState::Failed, Some(error));
+                continue;
+            }
+            if let Err(message) = start.profile.validate() {
+                let error =
+                    AppError::Other(format!("Profile \"{}\": {message}", start.profile.name));
+                self.download_queue
+                    .finish(start.id, JobState::Failed, Some(error));
+                continue;
+            }
+            let request = DownloadRequest {
+                job_id: start.id,
+                url: format!("https://www.youtube.com/watch?v={}", start.video_id),
+                download_path: self.settings_state.download_path.clone(),
+                output_template: resolve_app_fields(
+                    &self.settings_state.output_template,
+                    &start.title,
+                    start.playlist.as_ref(),
+                ),
+                profile: start.profile,
+                archive: if start.force {
+                    None
+                } else {
+                    self.history.archive_path()
+                },
+                tags: start.tags,
+                split: start.split,
+            };
+            let tx = self.tokio_worker.tx.clone();
+            let job_id = start.id;
+            let span = info_span!("download", job = job_id, video_id = %start.video_id);
+            tokio::spawn(
+                async move {
+                    info!(download_path = %request.download_path, force = start.force, "download started");
+                    let result = download_from_dlp(tx.clone(), &request, start.cancel).await;
+                    let message = match result {
+                        Ok(files) => {
+                            info!("download finished");
+                            WorkerMessage::Done(job_id, files)
+                        }
+                        Err(AppError::Cancelled) => {
+                            info!("download cancelled");
+                            WorkerMessage::DownloadCancelled(job_id)
+                        }
+                        Err(error) => {
+                            error!(%error, "download failed");
+                            WorkerMessage::Error {
+                                job: Some(job_id),
+                                error,
+                            }
+                        }
+                    };
+                    tx.send(message).await.unwrap();
+                }
+                .instrument(span),
+            );
+        }
+    }
+
+    pub fn render_queue(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("back").clicked() {
+                self.app_state = AppState::App;
+            }
+            ui.add_space(10.0);
+            ui.heading("Downloads");
         });
+        ui.add_space(20.0);
+        ui.horizontal(|ui| {
+            let pause_label = if self.download_queue.paused {
+                "▶ resume"
+            } else {
+                "⏸ pause"
+            };
+            if ui.button(pause_label).clicked() {
+                self.download_queue.paused = !self.download_queue.paused;
+            }
+            if ui.button("clear finished").clicked() {
+                self.download_queue.clear_finished();
+            }
+            if ui.button("🏷 tag files…").clicked() {
+                self.open_folder_tag_editor();
+            }
+            ui.add(
+                egui::Slider::new(&mut self.settings_state.max_concurrent_downloads, 1..=8)
+                    .text("parallel downloads"),
+            );
+        });
+        if self.download_queue.paused {
+            ui.colored_label(
+                Color32::GRAY,
+                "Queue paused – running downloads are finished, nothing new starts",
+            );
+        }
+        ui.add_space(20.0);
+
+        let mut cancel = None;
+        let mut retry = None;
+        let mut tag = None;
+        let mut cancel_group = None;
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                if self.download_queue.jobs().is_empty() && self.download_queue.groups().is_empty()
+                {
+                    ui.label("No downloads yet. Click a video in the results to queue it.");
+                }
+                // playlists without entries (still listing or failed) first
+                for group in self.download_queue.groups() {
+                    if self.download_queue.group_jobs(group.id).next().is_none() {
+                        playlist_header(ui, &self.download_queue, group, &mut cancel_group);
+                        ui.add_space(10.0);
+                    }
+                }
+                let mut shown_groups = Vec::new();
+                for job in self.download_queue.jobs() {
+                    let Some(group_id) = job.group else {
+                        job_row(ui, job, &mut cancel, &mut retry, &mut tag);
+                        ui.add_space(10.0);
+                        continue;
+                    };
+                    if shown_groups.contains(&group_id) {
+                        continue;
+                    }
+                    shown_groups.push(group_id);
+                    if let Some(group) = self.download_queue.group(group_id) {
+                        playlist_header(ui, &self.download_queue, group, &mut cancel_group);
+                    }
+                    ui.add_space(10.0);
+                    ui.indent(("playlist_jobs", group_id), |ui| {
+                        for job in self.download_queue.group_jobs(group_id) {
+                            job_row(ui, job, &mut cancel, &mut retry, &mut tag);
+                            ui.add_space(10.0);
+                        }
+                    });
+                }
+            });
+
+        if let Some(group) = cancel_group {
+            info!(group, "playlist cancel requested");
+            self.download_queue.cancel_group(group);
+        }
+        if let Some(id) = cancel {
+            info!(job = id, "cancel requested");
+            self.download_queue.cancel(id);
+        }
+        if let Some(id) = retry
+            && self.download_queue.retry(id)
+        {
+            info!(job = id, "download retried");
+        }
+        if let Some(job) = tag.and_then(|id| self.download_queue.get(id)) {
+            // suggest the tags of the download, else guess from the title
+            let tags = job
+                .tags
+                .clone()
+                .unwrap_or_else(|| TrackTags::guess(&job.title, "", ""));
+            self.open_tag_editor(TagTarget::Files(job.files.clone()), tags);
+        }
+    }
+
+    pub fn show_toast(&mut self, error: AppError) {
+        // don't stack the same message
+        self.toasts.retain(|toast| *toast != error);
+        self.toasts.push(error);
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+    }
+
+    // Dismissible error messages in the bottom right corner, some with a button that fixes them
+    pub fn render_toasts(&mut self, ctx: &egui::Context) {
+        let mut dismissed = None;
+        let mut action = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, vec2(-10.0, -10.0))
+            .show(ctx, |ui| {
+                for (index, error) in self.toasts.iter().enumerate() {
+                    egui::Frame::popup(ui.style())
+                        .stroke(egui::Stroke::new(1.0, Color32::RED))
+                        .show(ui, |ui| {
+                            ui.set_max_width(360.0);
+                            ui.horizontal(|ui| {
+                                error_label(ui, error);
+                                if ui.small_button("✕").clicked() {
+                                    dismissed = Some(index);
+                                }
+                            });
+                            let fix = match error {
+                                AppError::MissingKey | AppError::InvalidKey => {
+                                    Some(("enter API key", AppState::Warning))
+                                }
+                                AppError::QuotaExceeded | AppError::InvalidUrl(_) => {
+                                    Some(("open settings", AppState::Settings))
+                                }
+                                _ => None,
+                            };
+                            if let Some((label, state)) = fix
+                                && ui.button(label).clicked()
+                            {
+                                action = Some(state);
+                                dismissed = Some(index);
+                            }
+                        });
+                    ui.add_space(6.0);
+                }
+            });
+        if let Some(index) = dismissed {
+            self.toasts.remove(index);
+        }
+        if let Some(state) = action {
+            self.app_state = state;
+        }
+    }
+
+    // Without an API key the search goes through yt-dlp
+    pub fn active_backend(&self) -> SearchBackendKind {
+        effective_backend_kind(
+            self.settings_state.selected_backend(),
+            &self.settings_state.personal_yt_api,
+        )
     }
 
     pub fn render_settings(&mut self, ui: &mut egui::Ui) {
@@ -195,10 +1838,56 @@ impl YtGUI {
             });
         ui.label("settings");
         ui.add_space(40.0);
-        ui.add(egui::Slider::new(
-            &mut self.settings_state.max_results,
-            0..=25,
-        ));
+        ui.add(
+            egui::Slider::new(&mut self.settings_state.max_results, 1..=50)
+                .text("Results per page"),
+        );
+        ui.label("Search backend:");
+        self.render_search_profiles(ui);
+        let selected = self.settings_state.selected_backend();
+        if self.active_backend() != selected {
+            ui.colored_label(Color32::GRAY, "No API key set, falling back to yt-dlp");
+        }
+        if selected == SearchBackendKind::DataApi {
+            ui.label("API base URL (empty = googleapis.com):");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.settings_state.api_base_url)
+                    .hint_text(DEFAULT_API_BASE_URL),
+            );
+        }
+        ui.add(
+            egui::Slider::new(&mut self.settings_state.max_concurrent_downloads, 1..=8)
+                .text("Parallel downloads"),
+        );
+        ui.collapsing("Download profiles", |ui| self.render_profiles(ui));
+        ui.collapsing("File names", |ui| self.render_output_template(ui));
+        if ui.button("enter Api key").clicked() {
+            self.app_state = AppState::Warning;
+        }
+        ui.label("Log level:");
+        let log_level = self.settings_state.log_level;
+        egui::ComboBox::from_id_salt("log_level")
+            .selected_text(log_level.as_str())
+            .show_ui(ui, |ui| {
+                for level in LogLevel::ALL {
+                    ui.selectable_value(&mut self.settings_state.log_level, level, level.as_str());
+                }
+            });
+        if log_level != self.settings_state.log_level {
+            set_level(self.settings_state.log_level);
+        }
+        if std::env::var(LOG_ENV).is_ok() {
+            ui.colored_label(
+                Color32::GRAY,
+                format!("{LOG_ENV} is set and overrides this"),
+            );
+        }
+        if let Some(dir) = log_dir() {
+            ui.horizontal(|ui| {
+                ui.label("Log files:");
+                ui.monospace(dir.display().to_string());
+            });
+        }
         ui.label("Window Scaling:");
         ui.add(egui::Slider::new(&mut self.settings_state.window_scaling, 1.0..=4.0).text("Scale"));
         if ui.button("delete Api key").clicked() {
@@ -206,27 +1895,398 @@ impl YtGUI {
         }
         if ui.button("press me").clicked() {
             let output = std::process::Command::new("pwd").output();
-            println!("{:?}", output);
+            info!(?output, "pwd");
         }
         if ui.button("test me").clicked() {
             let output = std::process::Command::new(YT_DLP_BINARY)
                 .arg("--version")
                 .output();
-            println!("{:?}", output);
+            info!(?output, "yt-dlp --version");
+        }
+    }
+
+    fn render_audio_options(ui: &mut egui::Ui, profile: &mut DownloadProfile) {
+        egui::ComboBox::from_id_salt("codec")
+            .selected_text(profile.codec.label())
+            .show_ui(ui, |ui| {
+                for codec in AudioCodec::ALL {
+                    ui.selectable_value(&mut profile.codec, codec, codec.label());
+                }
+            });
+        if profile.codec.has_bitrate() {
+            let mut best = profile.bitrate_kbps.is_none();
+            if ui.checkbox(&mut best, "best VBR").changed() {
+                profile.bitrate_kbps = if best { None } else { Some(192) };
+            }
+            if let Some(kbps) = &mut profile.bitrate_kbps {
+                ui.add(egui::DragValue::new(kbps).range(32..=512).suffix(" kbit/s"));
+            }
+        }
+        let rate_label = match profile.sample_rate {
+            Some(rate) => format!("{rate} Hz"),
+            None => "original rate".to_string(),
+        };
+        egui::ComboBox::from_id_salt("sample_rate")
+            .selected_text(rate_label)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut profile.sample_rate, None, "original rate");
+                for rate in SAMPLE_RATES {
+                    ui.selectable_value(&mut profile.sample_rate, Some(rate), format!("{rate} Hz"));
+                }
+            });
+    }
+
+    // Video always gets the best audio merged in, so only the picture is configurable
+    fn render_video_options(ui: &mut egui::Ui, profile: &mut DownloadProfile) {
+        let height_label = match profile.max_height {
+            Some(height) => format!("≤{height}p"),
+            None => "best resolution".to_string(),
+        };
+        egui::ComboBox::from_id_salt("max_height")
+            .selected_text(height_label)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut profile.max_height, None, "best resolution");
+                for height in VIDEO_HEIGHTS {
+                    ui.selectable_value(
+                        &mut profile.max_height,
+                        Some(height),
+                        format!("≤{height}p"),
+                    );
+                }
+            });
+        egui::ComboBox::from_id_salt("video_codec")
+            .selected_text(profile.video_codec.label())
+            .show_ui(ui, |ui| {
+                for codec in VideoCodec::ALL {
+                    ui.add_enabled_ui(codec.fits(profile.container), |ui| {
+                        ui.selectable_value(&mut profile.video_codec, codec, codec.label());
+                    });
+                }
+            })
+            .response
+            .on_hover_text("preferred codec, others are used if it is not available");
+        egui::ComboBox::from_id_salt("container")
+            .selected_text(profile.container.api_value())
+            .show_ui(ui, |ui| {
+                for container in VideoContainer::ALL {
+                    ui.add_enabled_ui(profile.video_codec.fits(container), |ui| {
+                        ui.selectable_value(
+                            &mut profile.container,
+                            container,
+                            container.api_value(),
+                        );
+                    });
+                }
+            });
+    }
+
+    // Output template editor with a preview for one of the current results
+    pub fn render_output_template(&mut self, ui: &mut egui::Ui) {
+        ui.colored_label(
+            Color32::GRAY,
+            "yt-dlp fields like %(channel)s, / for folders. Existing files are never overwritten.",
+        );
+        let mut layouter = |ui: &Ui, buf: &dyn egui::TextBuffer, wrap_width: f32| {
+            let errors = validate(buf.as_str());
+            let mut job = highlight_errors(ui, buf.as_str(), errors.iter().map(|e| &e.span));
+            job.wrap.max_width = wrap_width;
+            ui.fonts(|f| f.layout_job(job))
+        };
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.settings_state.output_template)
+                    .code_editor()
+                    .desired_width(360.0)
+                    .layouter(&mut layouter),
+            );
+            if ui.button("reset").clicked() {
+                self.settings_state.output_template = DEFAULT_OUTPUT_TEMPLATE.to_string();
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            for field in YT_DLP_FIELDS {
+                let placeholder = match field {
+                    "playlist_index" => format!("%({field})03d"),
+                    _ => format!("%({field})s"),
+                };
+                if ui.small_button(field).on_hover_text(&placeholder).clicked() {
+                    self.settings_state.output_template.push_str(&placeholder);
+                }
+            }
+            for (field, description) in APP_FIELDS {
+                if ui
+                    .small_button(format!("{{{field}}}"))
+                    .on_hover_text(description)
+                    .clicked()
+                {
+                    self.settings_state
+                        .output_template
+                        .push_str(&format!("{{{field}}}"));
+                }
+            }
+        });
+
+        let errors = validate(&self.settings_state.output_template);
+        for error in &errors {
+            ui.colored_label(Color32::RED, &error.message);
+        }
+
+        // preview with a real result, else with a sample
+        let videos: Vec<&SearchItem> = self
+            .data
+            .items
+            .iter()
+            .filter(|item| item.id.video_id.is_some())
+            .collect();
+        self.template_preview = self.template_preview.min(videos.len().saturating_sub(1));
+        let mut values = match videos.get(self.template_preview) {
+            Some(item) => TemplateValues {
+                title: item.snippet.title.clone(),
+                id: item.id.video_id.clone().unwrap_or_default(),
+                channel: item.snippet.channel_title.clone(),
+                upload_date: item
+                    .snippet
+                    .published_at
+                    .get(..10)
+                    .unwrap_or_default()
+                    .replace('-', ""),
+                ..Default::default()
+            },
+            None => TemplateValues {
+                title: "Artist - Song Title".to_string(),
+                id: "dQw4w9WgXcQ".to_string(),
+                channel: "Channel".to_string(),
+                upload_date: "20240131".to_string(),
+                ..Default::default()
+            },
+        };
+        values.ext = self
+            .settings_state
+            .default_download_profile()
+            .extension()
+            .to_string();
+        if let ListingSource::Playlist { title, .. } = &self.listing {
+            values.playlist_title = Some(title.clone());
+            values.playlist_index = Some(self.template_preview as u32 + 1);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Preview for");
+            if videos.is_empty() {
+                ui.colored_label(
+                    Color32::GRAY,
+                    "an example (search for something to use a real result)",
+                );
+            } else {
+                egui::ComboBox::from_id_salt("template_preview")
+                    .selected_text(&values.title)
+                    .width(300.0)
+                    .show_ui(ui, |ui| {
+                        for (index, item) in videos.iter().enumerate() {
+                            ui.selectable_value(
+                                &mut self.template_preview,
+                                index,
+                                &item.snippet.title,
+                            );
+                        }
+                    });
+            }
+        });
+        if errors.is_empty() {
+            let preview = render_preview(&self.settings_state.output_template, &values);
+            let folder = self
+                .settings_state
+                .download_path
+                .trim_end_matches(['/', '\\']);
+            ui.monospace(format!("{folder}/{preview}"));
+        }
+    }
+
+    // Profile editor; a click on a result uses the default, right click offers all profiles
+    pub fn render_search_profiles(&mut self, ui: &mut egui::Ui) {
+        let profile_count = self.settings_state.search_profiles.len();
+        let mut remove = None;
+        for (index, profile) in self.settings_state.search_profiles.iter_mut().enumerate() {
+            ui.push_id(("search_profile", index), |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut self.settings_state.search_profile,
+                        profile.name.clone(),
+                        "",
+                    );
+                    let was_selected = self.settings_state.search_profile == profile.name;
+                    let name =
+                        ui.add(egui::TextEdit::singleline(&mut profile.name).desired_width(160.0));
+                    if name.changed() && was_selected {
+                        self.settings_state.search_profile = profile.name.clone();
+                    }
+                    egui::ComboBox::from_id_salt("kind")
+                        .selected_text(profile.kind.label())
+                        .show_ui(ui, |ui| {
+                            for kind in SearchBackendKind::ALL {
+                                ui.selectable_value(&mut profile.kind, kind, kind.label());
+                            }
+                        });
+                    if profile.kind.needs_instance() {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut profile.instance_url)
+                                .hint_text("https://instance.example"),
+                        );
+                    }
+                    if ui
+                        .add_enabled(profile_count > 1, Button::new("🗑"))
+                        .clicked()
+                    {
+                        remove = Some(index);
+                    }
+                });
+            });
+        }
+        if let Some(index) = remove {
+            self.settings_state.search_profiles.remove(index);
+        }
+        if ui.button("add search backend").clicked() {
+            self.settings_state.search_profiles.push(SearchProfile {
+                name: format!("Search {}", profile_count + 1),
+                ..Default::default()
+            });
+        }
+    }
+
+    pub fn render_profiles(&mut self, ui: &mut egui::Ui) {
+        ui.colored_label(
+            Color32::GRAY,
+            "Click a result to download it with the default profile, right click to pick another one.",
+        );
+        let profile_count = self.settings_state.download_profiles.len();
+        let mut remove = None;
+        for (index, profile) in self.settings_state.download_profiles.iter_mut().enumerate() {
+            ui.push_id(("profile", index), |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut self.settings_state.default_profile,
+                        profile.name.clone(),
+                        "",
+                    )
+                    .on_hover_text("default profile");
+                    let was_default = self.settings_state.default_profile == profile.name;
+                    let name =
+                        ui.add(egui::TextEdit::singleline(&mut profile.name).desired_width(160.0));
+                    // follow a rename of the default profile
+                    if name.changed() && was_default {
+                        self.settings_state.default_profile = profile.name.clone();
+                    }
+                    ui.selectable_value(&mut profile.mode, DownloadMode::Audio, "🎵 audio");
+                    ui.selectable_value(&mut profile.mode, DownloadMode::Video, "🎬 video");
+                    if ui
+                        .add_enabled(profile_count > 1, Button::new("🗑"))
+                        .clicked()
+                    {
+                        remove = Some(index);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.add_space(24.0);
+                    match profile.mode {
+                        DownloadMode::Audio => Self::render_audio_options(ui, profile),
+                        DownloadMode::Video => Self::render_video_options(ui, profile),
+                    }
+                    ui.add_enabled(
+                        profile.supports_thumbnail(),
+                        egui::Checkbox::new(&mut profile.embed_thumbnail, "cover"),
+                    );
+                    ui.checkbox(&mut profile.embed_metadata, "metadata");
+                    if let Err(message) = profile.validate() {
+                        ui.colored_label(Color32::RED, message);
+                    }
+                });
+            });
+            ui.add_space(6.0);
+        }
+        if let Some(index) = remove {
+            self.settings_state.download_profiles.remove(index);
+        }
+        if ui.button("add profile").clicked() {
+            self.settings_state.download_profiles.push(DownloadProfile {
+                name: format!("Profile {}", profile_count + 1),
+                ..Default::default()
+            });
+        }
+
+        ui.add_space(10.0);
+        ui.label("Import / export (JSON):");
+        ui.add(
+            egui::TextEdit::multiline(&mut self.profile_json)
+                .code_editor()
+                .desired_rows(4)
+                .hint_text("paste exported profiles here"),
+        );
+        ui.horizontal(|ui| {
+            if ui.button("export").clicked() {
+                self.profile_json = export_profiles(&self.settings_state.download_profiles);
+                ui.ctx().copy_text(self.profile_json.clone());
+                self.profile_status = Some(Ok("Copied to the clipboard".to_string()));
+            }
+            if ui.button("import").clicked() {
+                let result = import_profiles(
+                    &mut self.settings_state.download_profiles,
+                    &self.profile_json,
+                );
+                self.profile_status =
+                    Some(result.map(|count| format!("Imported {count} profile(s)")));
+            }
+        });
+        match &self.profile_status {
+            Some(Ok(message)) => {
+                ui.colored_label(Color32::GRAY, message);
+            }
+            Some(Err(message)) => {
+                ui.colored_label(Color32::RED, message);
+            }
+            None => {}
         }
     }
 
-    pub fn render_warning(&mut self, ui: &mut egui::Ui) {
+    pub fn render_warning(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
         ui.label("Warning no api Key found. Make sure you enter your Youtube API Key in here!");
+        let is_checking = matches!(self.key_check, KeyCheck::Checking);
         if !ui
-            .add(
-                egui::TextEdit::singleline(&mut self.settings_state.personal_yt_api)
-                    .hint_text("paste your api key"),
+            .add_enabled(
+                !is_checking,
+                egui::TextEdit::singleline(&mut self.key_input).hint_text("paste your api key"),
             )
             .has_focus()
             && ui.input(|i| i.key_pressed(egui::Key::Enter))
+            && !is_checking
+            && !self.key_input.trim().is_empty()
         {
-            println!("{}", self.settings_state.personal_yt_api);
+            self.key_check = KeyCheck::Checking;
+            let client = YouTubeClient::new(
+                self.http_client.clone(),
+                resolve_api_base_url(&self.settings_state.api_base_url),
+                self.key_input.trim().to_string(),
+            );
+            let tx = self.tokio_worker.tx.clone();
+            let ctx_giver = ctx.clone();
+            tokio::spawn(async move {
+                let result = client.validate_key().await;
+                tx.send(WorkerMessage::ApiKeyChecked(result)).await.unwrap();
+                ctx_giver.request_repaint();
+            });
+        }
+        match &self.key_check {
+            KeyCheck::Idle => {}
+            KeyCheck::Checking => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("checking key...");
+                });
+            }
+            KeyCheck::Failed(message) => {
+                ui.colored_label(Color32::RED, message);
+            }
+        }
+        if ui.button("continue without key (yt-dlp search)").clicked() {
             self.app_state = AppState::App;
         }
     }
@@ -243,9 +2303,6 @@ impl eframe::App for YtGUI {
             self.settings_state.first_run = false;
             self.settings_state.download_path = DOWNLOAD_PATH.to_string();
         }
-        if "" == self.settings_state.personal_yt_api {
-            self.app_state = AppState::Warning;
-        }
         let screen_rect = ctx.screen_rect();
         let panel_size = calc_grid_size(&screen_rect);
         self.side_width = panel_size.side_width;
@@ -255,19 +2312,116 @@ impl eframe::App for YtGUI {
             self.image_loader_installed = true
         }
 
-        if let Ok(msg) = self.tokio_worker.rx.try_recv() {
+        // handle all pending messages, not just one per frame
+        while let Ok(msg) = self.tokio_worker.rx.try_recv() {
             match msg {
-                WorkerMessage::Done(index) => {
-                    self.search_item[index].is_enabled = true;
+                WorkerMessage::Done(job, files) => {
+                    self.download_queue.finish(job, JobState::Done, None);
+                    self.record_download(job, files);
+                }
+                WorkerMessage::Error {
+                    job: Some(job),
+                    error,
+                } => {
+                    self.download_queue
+                        .finish(job, JobState::Failed, Some(error));
+                }
+                WorkerMessage::Error { job: None, error } => {
+                    self.is_loading_more = false;
+                    self.show_toast(error);
+                }
+                WorkerMessage::DownloadCancelled(job) => {
+                    self.download_queue.finish(job, JobState::Cancelled, None);
                 }
-                WorkerMessage::Progress(_progress_value) => {}
-                WorkerMessage::Error(_error_msg) => {}
-                WorkerMessage::Data(data) => {
-                    self.data = data;
+                WorkerMessage::Progress(job, progress) => {
+                    self.download_queue.set_progress(job, progress);
+                }
+                WorkerMessage::PlaylistEntries { group, result } => match result {
+                    Ok(playlist) => self.fill_playlist_group(group, playlist),
+                    Err(error) => self.download_queue.fail_group(group, error),
+                },
+                WorkerMessage::Metadata { video_id, result } => {
+                    // drop the reply for a window that was closed meanwhile
+                    if let Some(splitter) = &mut self.chapter_splitter
+                        && splitter.video_id == video_id
+                    {
+                        if let Ok(metadata) = &result {
+                            let description = metadata.description.as_deref().unwrap_or_default();
+                            splitter.chapters =
+                                clean_chapters(&metadata.chapters, metadata.duration);
+                            splitter.tracklist =
+                                clean_chapters(&parse_tracklist(description), metadata.duration);
+                            // official chapters take precedence
+                            splitter.source = if splitter.chapters.is_empty() {
+                                TrackSource::Description
+                            } else {
+                                TrackSource::Chapters
+                            };
+                            splitter.split = TrackSplit {
+                                chapters: match splitter.source {
+                                    TrackSource::Chapters => splitter.chapters.clone(),
+                                    TrackSource::Description => splitter.tracklist.clone(),
+                                },
+                                artist: splitter.guess.artist.clone(),
+                                album: splitter.guess.title.clone(),
+                                year: splitter.guess.year.clone(),
+                                output: TrackOutput::Files,
+                                cue_sheet: true,
+                                chapter_markers: true,
+                            };
+                        }
+                        splitter.metadata = Some(result.clone().map(|metadata| *metadata));
+                    }
+                    if let Some(inspector) = &mut self.format_inspector
+                        && inspector.video_id == video_id
+                    {
+                        inspector.metadata = Some(result.map(|metadata| *metadata));
+                    }
+                }
+                WorkerMessage::TagsWritten { files, result } => match result {
+                    Ok(()) => {
+                        info!(?files, "tags written");
+                        if let Some(editor) = &self.tag_editor
+                            && matches!(&editor.target, TagTarget::Files(target) if *target == files)
+                        {
+                            self.tag_editor = None;
+                        }
+                    }
+                    Err(error) => {
+                        error!(%error, "tags could not be written");
+                        if let Some(editor) = &mut self.tag_editor {
+                            editor.writing = false;
+                        }
+                        self.show_toast(error);
+                    }
+                },
+                WorkerMessage::Data { generation, data } => {
+                    self.receive_results(generation, data, false);
+                }
+                WorkerMessage::ApiKeyChecked(result) => match result {
+                    Ok(()) => {
+                        // only save after a successful test
+                        self.settings_state.personal_yt_api =
+                            std::mem::take(&mut self.key_input).trim().to_string();
+                        self.key_check = KeyCheck::Idle;
+                        self.app_state = AppState::App;
+                    }
+                    Err(error) => self.key_check = KeyCheck::Failed(error_text(&error)),
+                },
+                WorkerMessage::NextPage { generation, data } => {
+                    self.receive_results(generation, data, true);
                 }
             }
         }
 
+        self.start_queued_downloads();
+        // running downloads report progress without user input; a paused queue without
+        // running jobs has nothing to wait for
+        let queue = &self.download_queue;
+        if queue.active_count() > 0 || (!queue.paused && queue.pending_count() > 0) {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+
         match self.app_state {
             AppState::App => {
                 layout(self.side_width, ctx, |ui| self.search_bar(ctx, ui), false);
@@ -275,11 +2429,28 @@ impl eframe::App for YtGUI {
             AppState::Settings => {
                 layout(self.side_width, ctx, |ui| self.render_settings(ui), true);
             }
+            AppState::Queue => {
+                layout(self.side_width, ctx, |ui| self.render_queue(ui), false);
+            }
+            AppState::Formats => {
+                layout(self.side_width, ctx, |ui| self.render_formats(ui), false);
+            }
+            AppState::Chapters => {
+                layout(self.side_width, ctx, |ui| self.render_chapters(ui), false);
+            }
             AppState::Warning => {
-                layout(self.side_width, ctx, |ui| self.render_warning(ui), false);
+                layout(
+                    self.side_width,
+                    ctx,
+                    |ui| self.render_warning(ctx, ui),
+                    false,
+                );
             }
             AppState::Test => {}
         }
+        self.render_playlist_dialog(ctx);
+        self.render_tag_editor(ctx);
+        self.render_toasts(ctx);
     }
 }
 
@@ -331,6 +2502,239 @@ pub fn layout<Central>(
     });
 }
 
+// Text with the given error spans (invalid operators, template errors) underlined in red
+fn highlight_errors<'a>(
+    ui: &Ui,
+    text: &str,
+    spans: impl IntoIterator<Item = &'a Range<usize>>,
+) -> LayoutJob {
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let normal = egui::TextFormat::simple(font_id.clone(), ui.visuals().text_color());
+    let error = egui::TextFormat {
+        color: Color32::RED,
+        underline: egui::Stroke::new(1.0, Color32::RED),
+        ..egui::TextFormat::simple(font_id, Color32::RED)
+    };
+
+    let mut job = LayoutJob::default();
+    let mut position = 0;
+    for span in spans {
+        if span.start < position || span.end > text.len() {
+            continue;
+        }
+        job.append(&text[position..span.start], 0.0, normal.clone());
+        job.append(&text[span.clone()], 0.0, error.clone());
+        position = span.end;
+    }
+    job.append(&text[position..], 0.0, normal);
+    job
+}
+
+// Date input for the filter panel; turns red if the API would not accept it
+fn date_field(ui: &mut Ui, value: &mut String) {
+    let is_valid = value.trim().is_empty() || to_rfc3339(value).is_some();
+    let mut edit = egui::TextEdit::singleline(value)
+        .hint_text("YYYY-MM-DD")
+        .desired_width(100.0);
+    if !is_valid {
+        edit = edit.text_color(Color32::RED);
+    }
+    ui.add(edit);
+}
+
+// One download in the queue view
+fn job_row(
+    ui: &mut Ui,
+    job: &DownloadJob,
+    cancel: &mut Option<JobId>,
+    retry: &mut Option<JobId>,
+    tag: &mut Option<JobId>,
+) {
+    result_widget(ui, false, ("queue_job", job.id), |ui| {
+        ui.set_width(ui.available_width() - 10.0);
+        ui.horizontal(|ui| {
+            ui.vertical(|ui| {
+                ui.label(&job.title);
+                ui.colored_label(Color32::GRAY, &job.profile.name)
+                    .on_hover_text(job.profile.summary());
+                let color = match job.state {
+                    JobState::Failed => Color32::RED,
+                    JobState::Done => Color32::GREEN,
+                    _ => Color32::GRAY,
+                };
+                ui.colored_label(color, job.state.label());
+                for file in &job.files {
+                    if let Some(name) = file.file_name() {
+                        ui.colored_label(Color32::GRAY, name.to_string_lossy())
+                            .on_hover_text(file.display().to_string());
+                    }
+                }
+                if let Some(error) = &job.error {
+                    error_label(ui, error);
+                }
+                if job.state.is_active()
+                    && let Some(progress) = &job.progress
+                {
+                    download_progress_bar(ui, progress);
+                }
+            });
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                if !job.state.is_finished() && ui.button("cancel").clicked() {
+                    *cancel = Some(job.id);
+                }
+                if matches!(job.state, JobState::Failed | JobState::Cancelled)
+                    && ui.button("retry").clicked()
+                {
+                    *retry = Some(job.id);
+                }
+                if job.state == JobState::Done
+                    && !job.files.is_empty()
+                    // split tracks were tagged one by one already
+                    && job.split.is_none()
+                    && ui.button("🏷 tags").clicked()
+                {
+                    *tag = Some(job.id);
+                }
+            });
+        });
+    });
+}
+
+// Header of a playlist download: entry count, overall progress, cancel for all entries
+fn playlist_header(
+    ui: &mut Ui,
+    queue: &DownloadQueue,
+    group: &PlaylistGroup,
+    cancel_group: &mut Option<GroupId>,
+) {
+    result_widget(ui, false, ("queue_group", group.id), |ui| {
+        ui.set_width(ui.available_width() - 10.0);
+        ui.horizontal(|ui| {
+            ui.vertical(|ui| {
+                ui.colored_label(Color32::LIGHT_BLUE, "Playlist");
+                ui.label(&group.title);
+                ui.colored_label(Color32::GRAY, &group.profile.name)
+                    .on_hover_text(group.profile.summary());
+                let (finished, total, percent) = queue.group_progress(group.id);
+                match &group.state {
+                    GroupState::Enumerating => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("listing entries…");
+                        });
+                    }
+                    GroupState::Failed(error) => error_label(ui, error),
+                    GroupState::Ready => {
+                        let mut status = format!("{finished} of {total} finished");
+                        if group.skipped > 0 {
+                            status.push_str(&format!(
+                                ", {} skipped (downloaded before)",
+                                group.skipped
+                            ));
+                        }
+                        ui.colored_label(Color32::GRAY, status);
+                        if finished < total {
+                            ui.add(egui::ProgressBar::new(percent / 100.0).show_percentage());
+                        }
+                    }
+                }
+                if !group.range.is_empty() {
+                    ui.colored_label(Color32::GRAY, format!("entries {}", group.range));
+                }
+            });
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                let (finished, total, _) = queue.group_progress(group.id);
+                if finished < total && ui.button("cancel all").clicked() {
+                    *cancel_group = Some(group.id);
+                }
+            });
+        });
+    });
+}
+
+// File names carry no channel, so the artist comes from "Artist - Song" or stays empty
+fn guess_from_file(path: &std::path::Path) -> TrackTags {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    TrackTags::guess(&stem, "", "")
+}
+
+// "in library" marker of a result, details of the last download on hover
+fn library_badge(ui: &mut Ui, entry: Option<&HistoryEntry>) {
+    let badge = ui.colored_label(Color32::GREEN, "✔ in library");
+    match entry {
+        Some(entry) => {
+            let mut details = format!(
+                "downloaded {} with {} ({})\n{}",
+                format_date(entry.downloaded_at),
+                entry.profile,
+                entry.format,
+                format_bytes(entry.size as f64)
+            );
+            for file in &entry.files {
+                details.push_str(&format!("\n{}", file.display()));
+            }
+            badge.on_hover_text(details);
+        }
+        None => {
+            badge.on_hover_text("in the yt-dlp download archive");
+        }
+    }
+}
+
+// Red error text; the full yt-dlp stderr tail shows up on hover
+fn error_label(ui: &mut Ui, error: &AppError) {
+    let label = ui.colored_label(Color32::RED, error_text(error));
+    if let Some(details) = error.details() {
+        label.on_hover_text(redact(&details));
+    }
+}
+
+// Errors on screen end up in screenshots, so keys and URL credentials go like in the log
+fn error_text(error: &AppError) -> String {
+    redact(&error.to_string())
+}
+
+// Progress bar with size, speed and ETA below a result that is being downloaded
+fn download_progress_bar(ui: &mut Ui, progress: &DownloadProgress) {
+    match &progress.phase {
+        DownloadPhase::Downloading => {
+            ui.add(
+                egui::ProgressBar::new(progress.percent / 100.0)
+                    .desired_width(ui.available_width().min(300.0))
+                    .show_percentage(),
+            );
+            let mut details = Vec::new();
+            match (progress.downloaded_bytes, progress.total_bytes) {
+                (Some(done), Some(total)) => details.push(format!(
+                    "{} / {}",
+                    format_bytes(done as f64),
+                    format_bytes(total as f64)
+                )),
+                (Some(done), None) => details.push(format_bytes(done as f64)),
+                _ => {}
+            }
+            if let Some(speed) = progress.speed {
+                details.push(format!("{}/s", format_bytes(speed)));
+            }
+            if let Some(eta) = progress.eta {
+                details.push(format!("ETA {}", format_duration(eta)));
+            }
+            ui.colored_label(Color32::GRAY, details.join("  ·  "));
+        }
+        DownloadPhase::PostProcessing(postprocessor) => {
+            ui.add(
+                egui::ProgressBar::new(1.0)
+                    .desired_width(ui.available_width().min(300.0))
+                    .animate(true),
+            );
+            ui.colored_label(Color32::GRAY, format!("post-processing ({postprocessor})"));
+        }
+    }
+}
+
 pub fn result_widget<R>(
     ui: &mut Ui,
     button_state: bool,
@@ -357,3 +2761,72 @@ pub fn result_widget<R>(
     })
     .inner
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(video_ids: &[&str], next_page_token: &str) -> SearchResponse {
+        SearchResponse {
+            next_page_token: next_page_token.to_string(),
+            items: video_ids
+                .iter()
+                .map(|video_id| {
+                    SearchItem::new(Id::video(video_id.to_string()), Snippet::default(), None)
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn video_ids(app: &YtGUI) -> Vec<String> {
+        app.data
+            .items
+            .iter()
+            .filter_map(|item| item.id.video_id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn next_pages_are_appended() {
+        let mut app = YtGUI {
+            search_generation: 1,
+            ..Default::default()
+        };
+        app.receive_results(1, page(&["a", "b"], "page2"), false);
+        app.is_loading_more = true;
+        app.receive_results(1, page(&["c"], ""), true);
+        assert_eq!(video_ids(&app), ["a", "b", "c"]);
+        assert_eq!(app.search_item.len(), 3);
+        assert_eq!(app.data.next_page_token, "");
+        assert!(!app.is_loading_more);
+    }
+
+    #[test]
+    fn replies_of_older_searches_are_dropped() {
+        let mut app = YtGUI {
+            search_generation: 1,
+            ..Default::default()
+        };
+        app.receive_results(1, page(&["a"], "page2"), false);
+        // a new query or drill-down started while page 2 of the first was on its way
+        app.search_generation = 2;
+        app.receive_results(1, page(&["late"], ""), true);
+        app.receive_results(1, page(&["old"], ""), false);
+        assert_eq!(video_ids(&app), ["a"]);
+        assert_eq!(app.data.next_page_token, "page2");
+
+        app.receive_results(2, page(&["new"], ""), false);
+        assert_eq!(video_ids(&app), ["new"]);
+    }
+
+    #[test]
+    fn errors_on_screen_are_redacted() {
+        let error = AppError::Network(
+            "error sending request for url (https://host/videos?key=AIzaSyD-on-screen)".to_string(),
+        );
+        let text = error_text(&error);
+        assert!(!text.contains("AIzaSyD-on-screen"), "{text}");
+        assert!(text.contains("key=[REDACTED]"), "{text}");
+    }
+}
@@ -1,146 +1,693 @@
-use crate::share::{SearchResponse, WorkerMessage, YT_DLP_BINARY};
+use crate::duration::parse_iso8601_duration;
+use crate::error::{AppError, AppResult, STDERR_TAIL_LINES};
+use crate::logging::register_secret;
+use crate::metadata::{PlaylistMetadata, VideoMetadata};
+use crate::output_template::{PARTIAL_DIR, expand_path, move_into_place, unique_path};
+use crate::profiles::DownloadProfile;
+use crate::progress::{
+    DOWNLOAD_PROGRESS_TEMPLATE, POSTPROCESS_PROGRESS_TEMPLATE, parse_progress_line,
+};
+use crate::queue::JobId;
+use crate::share::{
+    DownloadPhase, DownloadProgress, FFMPEG_BINARY, PlaylistItemsResponse, SearchItem, SearchQuery,
+    SearchResponse, WorkerMessage, YT_DLP_BINARY, YtDlpEntry,
+};
+use crate::split::{TrackOutput, TrackSplit, supports_chapter_markers};
+use crate::tags::{TrackTags, is_media_file, media_files_below};
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde_json;
+use std::collections::VecDeque;
 use std::env;
 use std::error::Error;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tokio::io::AsyncBufReadExt;
+use tokio::sync::oneshot;
+use tracing::{Instrument, debug, info, trace, warn};
 
-pub async fn call_yt_api(query: String, max_results: i8) -> Result<SearchResponse, Box<dyn Error>> {
-    if let Ok(yt_key) = env::var("YT_API") {
-        println!("{}", yt_key);
-        let url = format!(
-            "https://www.googleapis.com/youtube/v3/search?part=snippet&q={}&key={}&maxResults={}&type=video&videCategoryId=10",
-            query.replace(" ", "%20"),
-            yt_key,
-            max_results
-        );
-        println!("{url}");
+// Shared HTTP client with timeouts and a user agent; reqwest::Client is cheap to clone
+pub fn build_http_client() -> Client {
+    Client::builder()
+        .user_agent(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(30))
+        .build()
+        .expect("failed to build http client")
+}
+
+pub const DEFAULT_API_BASE_URL: &str = "https://www.googleapis.com/youtube/v3";
+
+// Base URL from the settings, then YT_API_BASE_URL, then the real API.
+// Lets the app run against an API-compatible proxy or a local mock server.
+pub fn resolve_api_base_url(configured: &str) -> String {
+    let configured = configured.trim();
+    if !configured.is_empty() {
+        return configured.trim_end_matches('/').to_string();
+    }
+    match env::var("YT_API_BASE_URL") {
+        Ok(url) if !url.trim().is_empty() => url.trim().trim_end_matches('/').to_string(),
+        _ => DEFAULT_API_BASE_URL.to_string(),
+    }
+}
+
+// The one place that decides which API key the worker calls use:
+// key entered in the app, then YT_API from the environment, then YT_API from .env
+pub fn resolve_api_key(personal_yt_api: &str) -> Option<String> {
+    let personal_yt_api = personal_yt_api.trim();
+    let key = if !personal_yt_api.is_empty() {
+        Some(personal_yt_api.to_string())
+    } else {
+        // dotenv::var reads the environment; it loads .env but overrides nothing
+        dotenv::var("YT_API")
+            .ok()
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+    };
+    if let Some(key) = &key {
+        register_secret(key);
+    }
+    key
+}
+
+// YouTube Data API v3 client
+#[derive(Clone)]
+pub struct YouTubeClient {
+    http: Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl YouTubeClient {
+    pub fn new(http: Client, base_url: String, api_key: String) -> Self {
+        register_secret(&api_key);
+        Self {
+            http,
+            base_url,
+            api_key,
+        }
+    }
+
+    fn endpoint(
+        &self,
+        resource: &str,
+        mut params: Vec<(&'static str, String)>,
+    ) -> AppResult<reqwest::Url> {
+        if self.api_key.is_empty() {
+            warn!("no YouTube API key configured");
+            return Err(AppError::MissingKey);
+        }
+        params.push(("key", self.api_key.clone()));
+        let url = format!("{}/{resource}", self.base_url);
+        reqwest::Url::parse_with_params(&url, &params)
+            .map_err(|e| AppError::InvalidUrl(e.to_string()))
+    }
 
-        let client = Client::new();
-        let response = client.get(&url).send().await?;
-        if !response.status().is_success() {
-            println!("Request failed: {}", response.status());
+    // GET + JSON; error bodies are turned into the matching AppError.
+    // reqwest errors carry the request URL and with it the key, From<reqwest::Error> drops it.
+    async fn get_json<T: DeserializeOwned>(&self, url: reqwest::Url) -> AppResult<T> {
+        let response = self.http.get(url).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            warn!(%status, "request failed");
+            let body = response
+                .json::<serde_json::Value>()
+                .await
+                .unwrap_or_default();
+            return Err(AppError::from_api_error(status, &body));
         }
-        let data: SearchResponse = response.json::<SearchResponse>().await?;
-        println!("Alle Youtube Title: ");
-        let mut index: i8 = 1;
+        Ok(response.json::<T>().await?)
+    }
+
+    // Cheapest possible request (1 quota unit) to check a freshly entered key
+    pub async fn validate_key(&self) -> AppResult<()> {
+        let params = vec![
+            ("part", "id".to_string()),
+            ("id", "jNQXAC9IVRw".to_string()),
+        ];
+        let url = self.endpoint("videos", params)?;
+        self.get_json::<serde_json::Value>(url).await?;
+        Ok(())
+    }
+
+    pub async fn call_yt_api(
+        &self,
+        query: SearchQuery,
+        page_token: Option<String>,
+    ) -> AppResult<SearchResponse> {
+        let mut params = query.to_params();
+        // nextPageToken of the previous response -> next page
+        if let Some(token) = page_token {
+            params.push(("pageToken", token));
+        }
+        let url = self.endpoint("search", params)?;
+        debug!(%url, "GET");
+
+        let data: SearchResponse = self.get_json(url).await?;
+        debug!(results = data.items.len(), "search page received");
         for item in &data.items {
-            let video_title = &item.snippet.title;
-            println!("{index}: {video_title}");
-            index += 1;
+            trace!(title = %item.snippet.title, "result");
         }
         Ok(data)
-    } else {
-        println!("No key detected");
-        Err("YT_API Key not found".into())
     }
-}
 
-pub async fn set_video_durration(
-    video_id: Vec<String>,
-    meta_data: &mut SearchResponse,
-) -> Result<(), Box<dyn Error>> {
-    let final_string = video_id.join(",");
-    let key = env::var("YT_API").unwrap();
-    let url = format!(
-        "https://www.googleapis.com/youtube/v3/videos?part=contentDetails&id={final_string}&key={key}",
-    );
-    println!("{}", url);
-    let client = Client::new();
-    let response = client.get(&url).send().await?;
-    if !response.status().is_success() {
-        println!("Request failed: {}", response.status());
-    }
-    let data: serde_json::Value = response.json::<serde_json::Value>().await?;
-    if let Some(items) = data.get("items").and_then(|v| v.as_array()) {
-        for item in items {
-            if let (Some(video_id), Some(duration)) = (
-                item.get("id").and_then(|v| v.as_str()),
-                item.get("contentDetails")
-                    .and_then(|cd| cd.get("duration"))
-                    .and_then(|d| d.as_str()),
-            ) {
-                println!("{}", duration);
-                let formatted_duration = duration
-                    .replace("PT", "")
-                    .replace("H", ":")
-                    .replace("M", ":")
-                    .replace("S", "");
-                for item in meta_data.items.iter_mut() {
-                    if let Some(obj_video_id) = item.id.video_id.as_ref() {
-                        if obj_video_id == video_id {
-                            item.video_durration = Some(formatted_duration.clone());
+    // Videos of a playlist (playlistItems), as a SearchResponse for the result list
+    pub async fn call_playlist_items(
+        &self,
+        playlist_id: String,
+        max_results: i8,
+        page_token: Option<String>,
+    ) -> AppResult<SearchResponse> {
+        let mut params = vec![
+            ("part", "snippet".to_string()),
+            ("playlistId", playlist_id),
+            ("maxResults", max_results.to_string()),
+        ];
+        if let Some(token) = page_token {
+            params.push(("pageToken", token));
+        }
+        let url = self.endpoint("playlistItems", params)?;
+        let data: PlaylistItemsResponse = self.get_json(url).await?;
+        Ok(data.into())
+    }
+
+    // A channel's uploads live in a playlist of their own
+    pub async fn get_channel_uploads(&self, channel_id: String) -> AppResult<String> {
+        let params = vec![
+            ("part", "contentDetails".to_string()),
+            ("id", channel_id.clone()),
+        ];
+        let url = self.endpoint("channels", params)?;
+        let data: serde_json::Value = self.get_json(url).await?;
+        data.pointer("/items/0/contentDetails/relatedPlaylists/uploads")
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+            .ok_or_else(|| {
+                AppError::Other(format!(
+                    "The channel {channel_id} has no uploads or does not exist."
+                ))
+            })
+    }
+
+    pub async fn set_video_durration(
+        &self,
+        video_id: Vec<String>,
+        meta_data: &mut SearchResponse,
+    ) -> AppResult<()> {
+        let params = vec![
+            ("part", "contentDetails".to_string()),
+            ("id", video_id.join(",")),
+        ];
+        let url = self.endpoint("videos", params)?;
+        debug!(%url, "GET");
+        let data: serde_json::Value = self.get_json(url).await?;
+        if let Some(items) = data.get("items").and_then(|v| v.as_array()) {
+            for item in items {
+                if let (Some(video_id), Some(duration)) = (
+                    item.get("id").and_then(|v| v.as_str()),
+                    item.get("contentDetails")
+                        .and_then(|cd| cd.get("duration"))
+                        .and_then(|d| d.as_str()),
+                ) {
+                    trace!(video_id, duration, "video duration");
+                    let parsed_duration = parse_iso8601_duration(duration);
+                    for item in meta_data.items.iter_mut() {
+                        if let Some(obj_video_id) = item.id.video_id.as_ref()
+                            && obj_video_id == video_id
+                        {
+                            item.video_durration = parsed_duration;
                         }
                     }
                 }
             }
         }
+        Ok(())
     }
-    Ok(())
 }
 
+// Flat listing (ytsearch, playlist or channel) through yt-dlp; returns count entries from offset
+pub async fn call_yt_dlp_listing(
+    target: String,
+    offset: usize,
+    count: usize,
+) -> AppResult<SearchResponse> {
+    let items = format!("{}:{}", offset + 1, offset + count);
+    let stdout = run_yt_dlp_json(&[
+        "--flat-playlist",
+        "--dump-json",
+        "--playlist-items",
+        &items,
+        &target,
+    ])
+    .await?;
+    Ok(parse_listing(&stdout, offset, count))
+}
+
+// One page of `--dump-json` lines; only a full page can have another one after it,
+// which then starts at offset + count. Private or deleted videos still fill their slot.
+fn parse_listing(stdout: &str, offset: usize, count: usize) -> SearchResponse {
+    let entries: Vec<YtDlpEntry> = stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let full_page = entries.len() == count;
+    let items: Vec<SearchItem> = entries
+        .into_iter()
+        .filter_map(SearchItem::from_yt_dlp_entry)
+        .collect();
+    let next_page_token = if full_page {
+        (offset + count).to_string()
+    } else {
+        String::new()
+    };
+    SearchResponse {
+        next_page_token,
+        items,
+        ..Default::default()
+    }
+}
+
+// Full metadata of one video (formats, chapters, live status) via `yt-dlp -J`
+pub async fn fetch_metadata(url: &str) -> AppResult<VideoMetadata> {
+    let json = run_yt_dlp_json(&["-J", "--no-playlist", url]).await?;
+    Ok(VideoMetadata::parse(&json)?)
+}
+
+// Entries of a playlist without resolving each video (--flat-playlist)
+pub async fn fetch_playlist(playlist_id: &str) -> AppResult<PlaylistMetadata> {
+    let url = format!("https://www.youtube.com/playlist?list={playlist_id}");
+    let json = run_yt_dlp_json(&["--flat-playlist", "-J", &url]).await?;
+    Ok(PlaylistMetadata::parse(&json)?)
+}
+
+// Runs yt-dlp to completion and returns its JSON output; fails with its stderr
+async fn run_yt_dlp_json(args: &[&str]) -> AppResult<String> {
+    let output = tokio::process::Command::new(YT_DLP_BINARY)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| AppError::spawn_failed(YT_DLP_BINARY, e))?;
+    if !output.status.success() {
+        let stderr: Vec<String> = String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .map(str::to_string)
+            .collect();
+        return Err(AppError::yt_dlp_failed(output.status.code(), &stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+// Everything yt-dlp needs for one job
+pub struct DownloadRequest {
+    pub job_id: JobId,
+    pub url: String,
+    pub download_path: String,
+    // output template with the app fields already filled in
+    pub output_template: String,
+    pub profile: DownloadProfile,
+    // --download-archive file; None downloads even if the video is in the archive
+    pub archive: Option<PathBuf>,
+    // tags from the tag editor, written over what --embed-metadata put in
+    pub tags: Option<TrackTags>,
+    // tracks of a mix: cut into files or marked in the single file; replaces `tags`
+    pub split: Option<TrackSplit>,
+}
+
+// Runs one yt-dlp download and reports its progress. yt-dlp writes into a folder of its own
+// below the download folder; finished files are moved into place without overwriting
+// anything. When `cancel` fires yt-dlp or the ffmpeg post-processing is killed and
+// AppError::Cancelled is returned.
 pub async fn download_from_dlp(
     tx: tokio::sync::mpsc::Sender<WorkerMessage>,
-    item_id: usize,
-    url: &String,
-    download_path: &String,
-    audio_format: &'static str,
-) -> Result<(), Box<dyn Error>> {
-    let download_string = format!("{download_path}/%(title)s.%(ext)s");
-
-    let command = [
-        "-x",
-        "--audio-format",
-        audio_format,
-        "-o",
-        &download_string,
-        "--add-metadata",
-        "--embed-thumbnail",
-        "--ffmpeg-location",
-        "./ffmpeg/ffmpeg",
-        "--progress-template",
-        "download:%(progress)j",
-        "--progress-template",
-        "postprocess:%(progress)j",
+    request: &DownloadRequest,
+    cancel: oneshot::Receiver<()>,
+) -> AppResult<Vec<PathBuf>> {
+    let DownloadRequest {
+        job_id,
         url,
-    ];
+        download_path,
+        output_template,
+        profile,
+        archive,
+        tags,
+        split,
+    } = request;
+    let job_id = *job_id;
+    let download_dir = expand_path(download_path);
+    let partial_dir = download_dir.join(PARTIAL_DIR).join(format!("job-{job_id}"));
+    // leftovers of a cancelled run with the same job id
+    let _ = std::fs::remove_dir_all(&partial_dir);
+    let partial_path = partial_dir.to_string_lossy().into_owned();
+
+    let mut command = profile.yt_dlp_args();
+    command.extend(
+        [
+            "-P",
+            &partial_path,
+            "-o",
+            output_template,
+            "--windows-filenames",
+            "--ffmpeg-location",
+            FFMPEG_BINARY,
+            "--newline",
+            "--progress-template",
+            DOWNLOAD_PROGRESS_TEMPLATE,
+            "--progress-template",
+            POSTPROCESS_PROGRESS_TEMPLATE,
+            url,
+        ]
+        .map(str::to_string),
+    );
+    if let Some(archive) = archive {
+        // yt-dlp skips videos in the archive and records new ones itself
+        command.push("--download-archive".to_string());
+        command.push(archive.to_string_lossy().into_owned());
+    }
+    debug!(profile = %profile.name, ?command, "starting yt-dlp");
+
+    let mut cancel = CancelSignal(Some(cancel));
+    let mut result = run_yt_dlp(&tx, job_id, command, &mut cancel).await;
+    // tag while still in the partial folder, so an error leaves no half-done file
+    if result.is_ok() {
+        let post_processing = async {
+            if let Some(split) = split {
+                match split.output {
+                    TrackOutput::Files => split_tracks(&tx, job_id, &partial_dir, split).await,
+                    TrackOutput::SingleFile => mark_tracks(&partial_dir, split).await,
+                }
+            } else if let Some(tags) = tags {
+                write_tags(&media_files_below(&partial_dir), tags).await
+            } else {
+                Ok(())
+            }
+        };
+        // dropping the future kills a running ffmpeg (kill_on_drop)
+        result = tokio::select! {
+            result = post_processing => result,
+            () = cancel.requested() => Err(AppError::Cancelled),
+        };
+    }
+    if let Err(error) = result {
+        let _ = std::fs::remove_dir_all(&partial_dir);
+        return Err(error);
+    }
+    let mut files = move_into_place(&partial_dir, &download_dir)?;
+    info!(?files, "download moved into place");
+    // only now, so FILE names the final name
+    if let Some(split) = split
+        && split.output == TrackOutput::SingleFile
+        && split.cue_sheet
+    {
+        let cue_sheets: AppResult<Vec<PathBuf>> = files
+            .iter()
+            .filter(|file| is_media_file(file))
+            .map(|file| write_cue_sheet(file, split))
+            .collect();
+        files.extend(cue_sheets?);
+    }
+    Ok(files)
+}
 
+// "mix.mp3" -> "mix.cue" next to it
+fn write_cue_sheet(file: &Path, split: &TrackSplit) -> AppResult<PathBuf> {
+    let file_name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let path = unique_path(&file.with_extension("cue"));
+    std::fs::write(&path, split.cue_sheet(&file_name))?;
+    debug!(cue = %path.display(), "cue sheet written");
+    Ok(path)
+}
+
+// Rewrites the tags of finished files. The streams are copied, not encoded again;
+// ffmpeg cannot edit in place, so it writes a copy that replaces the original.
+pub async fn write_tags(files: &[PathBuf], tags: &TrackTags) -> AppResult<()> {
+    for file in files {
+        let temp = tagging_path(file);
+        let mut args: Vec<OsString> = vec![
+            "-v".into(),
+            "error".into(),
+            "-y".into(),
+            "-i".into(),
+            file.as_os_str().to_owned(),
+            // keep all streams, including the embedded cover
+            "-map".into(),
+            "0".into(),
+            "-c".into(),
+            "copy".into(),
+        ];
+        args.extend(tags.ffmpeg_args().into_iter().map(Into::into));
+        args.push(temp.as_os_str().to_owned());
+        debug!(file = %file.display(), ?args, "writing tags");
+        if let Err(error) = run_ffmpeg(args).await {
+            let _ = std::fs::remove_file(&temp);
+            return Err(error);
+        }
+        std::fs::rename(&temp, file)?;
+    }
+    Ok(())
+}
+
+// Cuts the downloaded file in `dir` into one tagged file per chapter and deletes it
+async fn split_tracks(
+    tx: &tokio::sync::mpsc::Sender<WorkerMessage>,
+    job_id: JobId,
+    dir: &Path,
+    split: &TrackSplit,
+) -> AppResult<()> {
+    let Some(source) = media_files_below(dir).into_iter().next() else {
+        return Err(AppError::Other(
+            "yt-dlp wrote no audio file that could be split".to_string(),
+        ));
+    };
+    let extension = source
+        .extension()
+        .map(|ext| ext.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    for index in 0..split.chapters.len() {
+        let progress = DownloadProgress {
+            phase: DownloadPhase::PostProcessing(format!(
+                "track {} of {}",
+                index + 1,
+                split.chapters.len()
+            )),
+            percent: 100.0,
+            ..Default::default()
+        };
+        tx.send(WorkerMessage::Progress(job_id, progress))
+            .await
+            .unwrap();
+        let target = dir.join(split.track_path(index, &extension));
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let args = split.ffmpeg_args(index, &source, &target);
+        debug!(track = index + 1, ?args, "splitting track");
+        run_ffmpeg(args).await?;
+    }
+    std::fs::remove_file(&source)?;
+    Ok(())
+}
+
+// Keeps the download as one file: album tags and, where the container has them,
+// chapter markers for the tracks
+async fn mark_tracks(dir: &Path, split: &TrackSplit) -> AppResult<()> {
+    let files = media_files_below(dir);
+    write_tags(&files, &split.album_tags()).await?;
+    if !split.chapter_markers {
+        return Ok(());
+    }
+    let chapters_file = dir.join("chapters.ffmetadata");
+    std::fs::write(&chapters_file, split.ffmetadata())?;
+    for file in &files {
+        let extension = file
+            .extension()
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if !supports_chapter_markers(&extension) {
+            warn!(file = %file.display(), "container has no chapters, markers skipped");
+            continue;
+        }
+        let temp = tagging_path(file);
+        let args: Vec<OsString> = vec![
+            "-v".into(),
+            "error".into(),
+            "-y".into(),
+            "-i".into(),
+            file.as_os_str().to_owned(),
+            "-i".into(),
+            chapters_file.as_os_str().to_owned(),
+            "-map".into(),
+            "0".into(),
+            "-map_metadata".into(),
+            "0".into(),
+            // chapters from the second input instead of YouTube's
+            "-map_chapters".into(),
+            "1".into(),
+            "-c".into(),
+            "copy".into(),
+            temp.as_os_str().to_owned(),
+        ];
+        debug!(file = %file.display(), ?args, "embedding chapter markers");
+        if let Err(error) = run_ffmpeg(args).await {
+            let _ = std::fs::remove_file(&temp);
+            return Err(error);
+        }
+        std::fs::rename(&temp, file)?;
+    }
+    std::fs::remove_file(&chapters_file)?;
+    Ok(())
+}
+
+// ffmpeg fails with its last stderr line, which usually names the problem
+async fn run_ffmpeg(args: Vec<OsString>) -> AppResult<()> {
+    let output = tokio::process::Command::new(FFMPEG_BINARY)
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| AppError::spawn_failed(FFMPEG_BINARY, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().last().unwrap_or("no output").to_string();
+        return Err(AppError::FfmpegFailed(reason));
+    }
+    Ok(())
+}
+
+// "song.mp3" -> "song.tagging.mp3"; ffmpeg picks the container from the extension
+fn tagging_path(file: &Path) -> PathBuf {
+    let stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match file.extension() {
+        Some(ext) => file.with_file_name(format!("{stem}.tagging.{}", ext.to_string_lossy())),
+        None => file.with_file_name(format!("{stem}.tagging")),
+    }
+}
+
+// Cancel request of one job. A dropped sender (queue cleared, job removed) is not a cancel,
+// the job just can't be stopped anymore.
+struct CancelSignal(Option<oneshot::Receiver<()>>);
+
+impl CancelSignal {
+    // Resolves when the job is cancelled, never if the sender is gone. A receiver that
+    // resolved is dropped, polling it again would panic.
+    async fn requested(&mut self) {
+        if let Some(receiver) = &mut self.0 {
+            let result = receiver.await;
+            self.0 = None;
+            if result.is_ok() {
+                return;
+            }
+        }
+        std::future::pending().await
+    }
+}
+
+// yt-dlp with progress forwarding and cancellation; fails with the stderr tail
+async fn run_yt_dlp(
+    tx: &tokio::sync::mpsc::Sender<WorkerMessage>,
+    job_id: JobId,
+    command: Vec<String>,
+    cancel: &mut CancelSignal,
+) -> AppResult<()> {
     let mut output = tokio::process::Command::new(YT_DLP_BINARY)
-        .args(&command)
+        .args(command)
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
-        .spawn()?;
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| AppError::spawn_failed(YT_DLP_BINARY, e))?;
+
+    // read stderr concurrently, otherwise yt-dlp blocks on a full pipe.
+    // The last lines end up in the error message.
+    let stderr_tail = output.stderr.take().map(|stderr| {
+        tokio::spawn(
+            async move {
+                let mut tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+                let mut lines = tokio::io::BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    warn!(%line, "yt-dlp stderr");
+                    if tail.len() == STDERR_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+                Vec::from(tail)
+            }
+            .in_current_span(),
+        )
+    });
 
     if let Some(stdout) = output.stdout.take() {
         let reader = tokio::io::BufReader::new(stdout);
         let mut lines = reader.lines();
+        let mut last_sent: Option<(Instant, DownloadProgress)> = None;
 
-        while let Some(line) = lines.next_line().await? {
-            match serde_json::from_str::<serde_json::Value>(&line) {
-                Ok(progress) => {
-                    if let Some(procent) = progress.get("_percent_str") {
-                        println!("{procent}")
-                    }
+        loop {
+            let line = tokio::select! {
+                line = lines.next_line() => line?,
+                () = cancel.requested() => {
+                    output.kill().await?;
+                    return Err(AppError::Cancelled);
+                }
+            };
+            let Some(line) = line else {
+                break;
+            };
+            let Some(progress) = parse_progress_line(&line) else {
+                trace!(%line, "yt-dlp stdout");
+                continue;
+            };
+            // yt-dlp reports very often; the UI gets an update at most every 200ms,
+            // phase changes and 100% always
+            let is_due = match &last_sent {
+                Some((sent_at, last)) => {
+                    sent_at.elapsed() >= PROGRESS_INTERVAL
+                        || last.phase != progress.phase
+                        || progress.percent >= 100.0
                 }
-                Err(_) => {}
+                None => true,
+            };
+            if is_due {
+                debug!(percent = progress.percent, phase = ?progress.phase, "download progress");
+                tx.send(WorkerMessage::Progress(job_id, progress.clone()))
+                    .await
+                    .unwrap();
+                last_sent = Some((Instant::now(), progress));
             }
         }
     }
-    if let Some(stderr) = output.stderr.take() {
-        let reader = tokio::io::BufReader::new(stderr);
-        let mut lines = reader.lines();
 
-        while let Some(line) = lines.next_line().await? {
-            println!("{line}");
+    let status = tokio::select! {
+        status = output.wait() => status?,
+        () = cancel.requested() => {
+            output.kill().await?;
+            return Err(AppError::Cancelled);
         }
+    };
+    if !status.success() {
+        let stderr = match stderr_tail {
+            Some(task) => task.await.unwrap_or_default(),
+            None => Vec::new(),
+        };
+        return Err(AppError::yt_dlp_failed(status.code(), &stderr));
     }
-
-    tx.send(WorkerMessage::Done(item_id)).await.unwrap();
     Ok(())
 }
 
 pub async fn test_io() -> Result<(), Box<dyn Error>> {
-    println!("starting test_io");
+    debug!("starting test_io");
     let mut child = tokio::process::Command::new("ping")
         .arg("google.com")
         .stdout(std::process::Stdio::piped())
@@ -152,8 +699,76 @@ pub async fn test_io() -> Result<(), Box<dyn Error>> {
         let mut lines = reader.lines();
 
         while let Some(line) = lines.next_line().await? {
-            println!("STDOUT: {}", line);
+            debug!(%line, "test_io stdout");
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAT_SEARCH: &str = include_str!("../tests/fixtures/yt_dlp_flat_search.jsonl");
+
+    #[test]
+    fn flat_entries_become_search_items() {
+        let data = parse_listing(FLAT_SEARCH, 0, 3);
+        // the private video without an id is dropped
+        assert_eq!(data.items.len(), 2);
+
+        let video = &data.items[0];
+        assert_eq!(video.id.video_id.as_deref(), Some("5NV6Rdv1a3I"));
+        assert_eq!(video.snippet.channel_title, "Daft Punk");
+        assert_eq!(video.snippet.channel_id, "UC_kRDKYrUlrbtrSiyu5Tflg");
+        assert_eq!(video.snippet.live_broadcast_content, "none");
+        assert_eq!(video.video_durration, Some(Duration::from_secs(369)));
+        let thumbnail = video.snippet.thumbnails.default.as_ref().unwrap();
+        assert_eq!(thumbnail.width, Some(360));
+
+        // livestream: no duration, uploader as the channel, default thumbnail
+        let live = &data.items[1];
+        assert_eq!(live.snippet.channel_title, "Lofi Girl");
+        assert_eq!(live.snippet.live_broadcast_content, "live");
+        assert_eq!(live.video_durration, None);
+        assert_eq!(
+            live.snippet.thumbnails.default.as_ref().unwrap().url,
+            "https://i.ytimg.com/vi/jfKfPfyJRdk/default.jpg"
+        );
+    }
+
+    #[test]
+    fn only_full_pages_continue() {
+        let two_entries: String = FLAT_SEARCH.lines().take(2).collect::<Vec<_>>().join("\n");
+        assert_eq!(parse_listing(&two_entries, 20, 2).next_page_token, "22");
+        assert_eq!(parse_listing(&two_entries, 20, 5).next_page_token, "");
+        assert_eq!(parse_listing("", 0, 5).items.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn dropped_cancel_sender_is_not_a_cancel() {
+        let (sender, receiver) = oneshot::channel::<()>();
+        let mut cancel = CancelSignal(Some(receiver));
+        drop(sender);
+        // the second round polls again after the receiver resolved with an error
+        for _ in 0..2 {
+            tokio::select! {
+                biased;
+                () = cancel.requested() => panic!("a dropped sender cancelled the job"),
+                () = tokio::task::yield_now() => {}
+            }
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        let mut cancel = CancelSignal(Some(receiver));
+        sender.send(()).unwrap();
+        cancel.requested().await;
+    }
+
+    #[test]
+    fn entries_without_id_still_count_towards_a_full_page() {
+        let data = parse_listing(FLAT_SEARCH, 20, 3);
+        assert_eq!(data.items.len(), 2);
+        assert_eq!(data.next_page_token, "23");
+    }
+}